@@ -0,0 +1,35 @@
+//! Compares the scalar and vector escape-time loops of `calc_iterations`.
+//! Run with `cargo bench --bench escape_time`
+
+use std::time::Instant;
+use wasm::mandelbrot::{calc_iterations, simd, FractalParams, RenderOptions};
+
+fn time(simd: bool, region: &[f64], runs: u32) -> f64 {
+    let mut options = RenderOptions::new();
+    options.iterations = 500;
+    options.simd = simd;
+    let fractal = FractalParams::default();
+    let start = Instant::now();
+    for _ in 0..runs {
+        std::hint::black_box(calc_iterations(400, 300, region, &fractal, &options));
+    }
+    start.elapsed().as_secs_f64() * 1000.0 / runs as f64
+}
+
+fn main() {
+    println!("vector lanes: {}", simd::VECTOR);
+    for (name, region) in [
+        ("whole set", [-2.0, 1.0, -1.2, 1.2]),
+        ("seahorse valley", [-0.76, -0.73, 0.09, 0.12]),
+    ] {
+        let scalar = time(false, &region, 5);
+        let vector = time(true, &region, 5);
+        println!(
+            "{:<16} scalar {:>8.2} ms  simd {:>8.2} ms  x{:.2}",
+            name,
+            scalar,
+            vector,
+            scalar / vector
+        );
+    }
+}
@@ -0,0 +1,40 @@
+//! Compares the exact repulsion of `ParticleWorld::evolve` with its Barnes–Hut approximation
+//! and with a cutoff radius.
+//! Run with `cargo bench --bench particles`
+
+use std::time::Instant;
+use wasm::particles::random_world;
+
+const PARTICLES: usize = 10_000;
+
+fn time(theta: f32, cutoff: f32, steps: u32) -> f64 {
+    let mut world = random_world(100.0, 100.0, PARTICLES);
+    world.set_theta(theta);
+    //the default forces
+    world.set_forces(200.0, 1.5, cutoff);
+    let start = Instant::now();
+    for _ in 0..steps {
+        world.evolve();
+    }
+    std::hint::black_box(world.points());
+    start.elapsed().as_secs_f64() * 1000.0 / steps as f64
+}
+
+fn main() {
+    let exact = time(0.0, f32::INFINITY, 2);
+    println!("{} particles, ms per step", PARTICLES);
+    println!("exact          {:>9.2}", exact);
+    for theta in [0.3, 0.5, 0.8] {
+        let approximate = time(theta, f32::INFINITY, 10);
+        println!(
+            "theta {:<8} {:>9.2}  x{:.1}",
+            theta,
+            approximate,
+            exact / approximate
+        );
+    }
+    for cutoff in [5.0, 10.0] {
+        let short = time(0.0, cutoff, 10);
+        println!("cutoff {:<7} {:>9.2}  x{:.1}", cutoff, short, exact / short);
+    }
+}
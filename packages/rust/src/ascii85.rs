@@ -0,0 +1,199 @@
+use crate::codec::{decode_table, wasm_codec, Codec, DecodeError, INVALID};
+use wasm_bindgen::prelude::*;
+
+static ASCII85_TABLE: [u8; 85] = {
+    let mut table = [0u8; 85];
+    let mut i = 0;
+    while i < 85 {
+        table[i] = b'!' + i as u8;
+        i += 1;
+    }
+    table
+};
+
+static Z85_TABLE: [u8; 85] =
+    *b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ.-:+=^!/*?&<>()[]{}@%$#";
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ascii85Variant {
+    /// `!` to `u`, `z` for a group of zeros and whitespace is ignored when decoding (btoa / Adobe)
+    Ascii85,
+    /// the ZeroMQ alphabet (RFC 32), safe to embed in source code and XML
+    Z85,
+}
+
+/// Base85, 4 bytes to 5 characters.
+/// A last group of n < 4 bytes is encoded as n + 1 characters, for Z85 too,
+/// which on its own only defines inputs with a multiple of 4 bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ascii85 {
+    variant: Ascii85Variant,
+    encode: [u8; 85],
+    decode: [u8; 256],
+}
+
+impl Ascii85 {
+    pub const ASCII85: Ascii85 = Ascii85::new(Ascii85Variant::Ascii85);
+    pub const Z85: Ascii85 = Ascii85::new(Ascii85Variant::Z85);
+
+    pub const fn new(variant: Ascii85Variant) -> Self {
+        let encode = match variant {
+            Ascii85Variant::Ascii85 => ASCII85_TABLE,
+            Ascii85Variant::Z85 => Z85_TABLE,
+        };
+        Self {
+            variant,
+            encode,
+            decode: decode_table(&encode),
+        }
+    }
+
+    fn push_group(&self, out: &mut Vec<u8>, group: &[u8]) {
+        let mut block = [0u8; 4];
+        block[..group.len()].copy_from_slice(group);
+        let mut value = u32::from_be_bytes(block);
+        if self.variant == Ascii85Variant::Ascii85 && group.len() == 4 && value == 0 {
+            out.push(b'z');
+            return;
+        }
+        let mut digits = [0u8; 5];
+        for digit in digits.iter_mut().rev() {
+            *digit = self.encode[(value % 85) as usize];
+            value /= 85;
+        }
+        out.extend_from_slice(&digits[..group.len() + 1]);
+    }
+}
+
+impl Default for Ascii85 {
+    fn default() -> Self {
+        Self::ASCII85
+    }
+}
+
+impl Codec for Ascii85 {
+    fn encoded_len(&self, len: usize) -> usize {
+        let tail = len % 4;
+        len / 4 * 5 + if tail == 0 { 0 } else { tail + 1 }
+    }
+
+    fn encode_into(&self, bytes: &[u8], out: &mut Vec<u8>) {
+        out.reserve(self.encoded_len(bytes.len()));
+        bytes
+            .chunks(4)
+            .for_each(|group| self.push_group(out, group));
+    }
+
+    fn decode_into(&self, text: &[u8], out: &mut Vec<u8>) -> Result<(), DecodeError> {
+        let ascii85 = self.variant == Ascii85Variant::Ascii85;
+        let mut digits = [0u8; 5];
+        let mut filled = 0;
+        let mut symbols = 0;
+        let mut last_index = 0;
+        out.reserve(text.len() / 5 * 4);
+        for (index, &byte) in text.iter().enumerate() {
+            if ascii85 && byte.is_ascii_whitespace() {
+                continue;
+            }
+            if ascii85 && byte == b'z' && filled == 0 {
+                out.extend_from_slice(&[0; 4]);
+                continue;
+            }
+            digits[filled] = match self.decode[byte as usize] {
+                INVALID => return Err(DecodeError::InvalidSymbol { index, byte }),
+                value => value,
+            };
+            filled += 1;
+            symbols += 1;
+            last_index = index;
+            if filled == 5 {
+                out.extend_from_slice(&group_value(&digits, index)?.to_be_bytes());
+                filled = 0;
+            }
+        }
+        match filled {
+            0 => Ok(()),
+            1 => Err(DecodeError::InvalidLength(symbols)),
+            _ => {
+                //the encoder truncated the digits, so the largest digit restores the value
+                digits[filled..].fill(84);
+                let bytes = group_value(&digits, last_index)?.to_be_bytes();
+                out.extend_from_slice(&bytes[..filled - 1]);
+                Ok(())
+            }
+        }
+    }
+}
+
+fn group_value(digits: &[u8; 5], index: usize) -> Result<u32, DecodeError> {
+    let value = digits
+        .iter()
+        .fold(0u64, |acc, &digit| acc * 85 + digit as u64);
+    u32::try_from(value).map_err(|_| DecodeError::Overflow(index))
+}
+
+wasm_codec!(Ascii85Decoder, Ascii85, to_ascii85, from_ascii85);
+
+#[wasm_bindgen]
+impl Ascii85Decoder {
+    pub fn new(variant: Ascii85Variant) -> Self {
+        Self::with_codec(Ascii85::new(variant))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Ascii85, Ascii85Decoder, Ascii85Variant};
+    use crate::codec::{assert_round_trip, Codec, DecodeError};
+
+    #[test]
+    fn known_vectors() {
+        let mut ascii85 = Ascii85Decoder::new(Ascii85Variant::Ascii85);
+        ascii85.to_ascii85(b"Man is d");
+        assert_eq!(ascii85.output(), b"9jqo^BlbD-");
+        ascii85.to_ascii85(&[0, 0, 0, 0, 0, 0]);
+        assert_eq!(ascii85.output(), b"z!!!");
+
+        let mut z85 = Ascii85Decoder::new(Ascii85Variant::Z85);
+        z85.to_ascii85(&[0x86, 0x4f, 0xd2, 0x6f, 0xb5, 0x59, 0xf7, 0x5b]);
+        assert_eq!(z85.output(), b"HelloWorld");
+
+        assert_round_trip(&Ascii85::ASCII85);
+        assert_round_trip(&Ascii85::Z85);
+    }
+
+    #[test]
+    fn decode_errors() {
+        let decode = |codec: Ascii85, text: &[u8]| {
+            let mut out = vec![];
+            codec.decode_into(text, &mut out).map(|_| out)
+        };
+        assert_eq!(
+            decode(Ascii85::ASCII85, b"9jqo^\n Bl bD-"),
+            Ok(b"Man is d".to_vec())
+        );
+        assert_eq!(
+            decode(Ascii85::ASCII85, b"9jqo^B"),
+            Err(DecodeError::InvalidLength(6))
+        );
+        assert_eq!(
+            decode(Ascii85::ASCII85, b"s8W-\""),
+            Err(DecodeError::Overflow(4))
+        );
+        assert_eq!(
+            decode(Ascii85::ASCII85, b"9jzo^"),
+            Err(DecodeError::InvalidSymbol {
+                index: 2,
+                byte: b'z'
+            })
+        );
+        assert_eq!(
+            decode(Ascii85::Z85, b"Hello World"),
+            Err(DecodeError::InvalidSymbol {
+                index: 5,
+                byte: b' '
+            })
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use crate::base64::Padding;
+use crate::codec::{decode_symbol, decode_table, wasm_codec, Codec, DecodeError};
+use wasm_bindgen::prelude::*;
+
+static BASE32_TABLE: [u8; 32] = *b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+static BASE32_HEX_TABLE: [u8; 32] = *b"0123456789ABCDEFGHIJKLMNOPQRSTUV";
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Base32Alphabet {
+    /// `A-Z2-7` (RFC 4648 section 6)
+    Standard,
+    /// `0-9A-V`, keeps the sort order of the data (RFC 4648 section 7)
+    Hex,
+}
+
+/// RFC 4648 base32, 5 bytes to 8 characters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Base32 {
+    encode: [u8; 32],
+    decode: [u8; 256],
+    padding: Padding,
+}
+
+impl Base32 {
+    pub const STANDARD: Base32 = Base32::new(Base32Alphabet::Standard, Padding::Padded);
+    pub const HEX: Base32 = Base32::new(Base32Alphabet::Hex, Padding::Padded);
+
+    pub const fn new(alphabet: Base32Alphabet, padding: Padding) -> Self {
+        let encode = match alphabet {
+            Base32Alphabet::Standard => BASE32_TABLE,
+            Base32Alphabet::Hex => BASE32_HEX_TABLE,
+        };
+        Self {
+            encode,
+            decode: decode_table(&encode),
+            padding,
+        }
+    }
+}
+
+impl Default for Base32 {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+//bytes encoded by a last group of n symbols, None for lengths the encoder never produces
+fn tail_bytes(symbols: usize) -> Option<usize> {
+    match symbols {
+        0 => Some(0),
+        2 => Some(1),
+        4 => Some(2),
+        5 => Some(3),
+        7 => Some(4),
+        8 => Some(5),
+        _ => None,
+    }
+}
+
+impl Codec for Base32 {
+    fn encoded_len(&self, len: usize) -> usize {
+        match self.padding {
+            Padding::Padded => len.div_ceil(5) * 8,
+            Padding::Unpadded => (len * 8).div_ceil(5),
+        }
+    }
+
+    fn encode_into(&self, bytes: &[u8], out: &mut Vec<u8>) {
+        out.reserve(self.encoded_len(bytes.len()));
+        for group in bytes.chunks(5) {
+            let mut block = [0u8; 8];
+            block[3..3 + group.len()].copy_from_slice(group);
+            let bits40 = u64::from_be_bytes(block);
+            let symbols = (group.len() * 8).div_ceil(5);
+            for i in 0..symbols {
+                let index = (bits40 >> (35 - 5 * i)) & 0b11111;
+                out.push(self.encode[index as usize]);
+            }
+            if self.padding == Padding::Padded {
+                out.resize(out.len() + 8 - symbols, b'=');
+            }
+        }
+    }
+
+    fn decode_into(&self, text: &[u8], out: &mut Vec<u8>) -> Result<(), DecodeError> {
+        let padding = text.iter().rev().take_while(|&&c| c == b'=').count();
+        let body = &text[..text.len() - padding];
+        let tail = body.len() % 8;
+        match self.padding {
+            Padding::Padded if !text.len().is_multiple_of(8) => {
+                return Err(DecodeError::InvalidLength(text.len()))
+            }
+            Padding::Padded if padding != (8 - tail) % 8 => {
+                return Err(DecodeError::InvalidPadding(body.len()))
+            }
+            Padding::Unpadded if padding > 0 => {
+                return Err(DecodeError::InvalidPadding(body.len()))
+            }
+            _ => {}
+        }
+        if tail_bytes(tail).is_none() {
+            return Err(DecodeError::InvalidLength(body.len()));
+        }
+
+        out.reserve(body.len() / 8 * 5 + 4);
+        for (group_index, group) in body.chunks(8).enumerate() {
+            let offset = group_index * 8;
+            let mut bits40 = 0u64;
+            for (i, &byte) in group.iter().enumerate() {
+                bits40 |= (decode_symbol(&self.decode, byte, offset + i)? as u64) << (35 - 5 * i);
+            }
+            let len = tail_bytes(group.len()).unwrap_or(0);
+            if bits40 & ((1u64 << (40 - 8 * len)) - 1) != 0 {
+                return Err(DecodeError::TrailingBits(offset + group.len() - 1));
+            }
+            out.extend_from_slice(&bits40.to_be_bytes()[3..3 + len]);
+        }
+        Ok(())
+    }
+}
+
+wasm_codec!(Base32Decoder, Base32, to_base32, from_base32);
+
+#[wasm_bindgen]
+impl Base32Decoder {
+    pub fn new(alphabet: Base32Alphabet, padding: Padding) -> Self {
+        Self::with_codec(Base32::new(alphabet, padding))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Base32, Base32Alphabet, Base32Decoder};
+    use crate::base64::Padding;
+    use crate::codec::{assert_round_trip, Codec, DecodeError};
+
+    #[test]
+    fn rfc4648_vectors() {
+        let vectors: [(&[u8], &[u8], &[u8]); 7] = [
+            (b"", b"", b""),
+            (b"f", b"MY======", b"CO======"),
+            (b"fo", b"MZXQ====", b"CPNG===="),
+            (b"foo", b"MZXW6===", b"CPNMU==="),
+            (b"foob", b"MZXW6YQ=", b"CPNMUOG="),
+            (b"fooba", b"MZXW6YTB", b"CPNMUOJ1"),
+            (b"foobar", b"MZXW6YTBOI======", b"CPNMUOJ1E8======"),
+        ];
+        let mut standard = Base32Decoder::new(Base32Alphabet::Standard, Padding::Padded);
+        let mut hex = Base32Decoder::new(Base32Alphabet::Hex, Padding::Padded);
+        for (bytes, text, hex_text) in vectors {
+            standard.to_base32(bytes);
+            assert_eq!(standard.output(), text);
+            hex.to_base32(bytes);
+            assert_eq!(hex.output(), hex_text);
+        }
+        for codec in [
+            Base32::STANDARD,
+            Base32::HEX,
+            Base32::new(Base32Alphabet::Standard, Padding::Unpadded),
+        ] {
+            assert_round_trip(&codec);
+        }
+    }
+
+    #[test]
+    fn decode_errors() {
+        let decode = |codec: Base32, text: &[u8]| codec.decode_into(text, &mut vec![]);
+        let unpadded = Base32::new(Base32Alphabet::Standard, Padding::Unpadded);
+        assert_eq!(
+            decode(Base32::STANDARD, b"MZXW6"),
+            Err(DecodeError::InvalidLength(5))
+        );
+        assert_eq!(
+            decode(Base32::STANDARD, b"MZXW6YQ==="),
+            Err(DecodeError::InvalidLength(10))
+        );
+        assert_eq!(
+            decode(Base32::STANDARD, b"MZXW6Y=="),
+            Err(DecodeError::InvalidLength(6))
+        );
+        assert_eq!(
+            decode(Base32::STANDARD, b"MY=====A"),
+            Err(DecodeError::InvalidPadding(2))
+        );
+        assert_eq!(decode(unpadded, b"MZX"), Err(DecodeError::InvalidLength(3)));
+        assert_eq!(
+            decode(unpadded, b"MY=="),
+            Err(DecodeError::InvalidPadding(2))
+        );
+        assert_eq!(
+            decode(Base32::STANDARD, b"MZ======"),
+            Err(DecodeError::TrailingBits(1))
+        );
+        assert_eq!(
+            decode(Base32::HEX, b"MZXW6==="),
+            Err(DecodeError::InvalidSymbol {
+                index: 1,
+                byte: b'Z'
+            })
+        );
+    }
+}
@@ -0,0 +1,417 @@
+mod simd;
+mod stream;
+
+use crate::codec::{decode_symbol, decode_table, wasm_codec, Codec};
+use wasm_bindgen::prelude::*;
+
+pub use crate::codec::DecodeError as Base64Error;
+pub use stream::{Base64StreamDecoder, Base64StreamEncoder};
+
+static BASE64_TABLE: [u8; 64] =
+    *b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+static BASE64_URL_TABLE: [u8; 64] =
+    *b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+const MIME_LINE_WIDTH: usize = 76;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alphabet {
+    /// `+` and `/` for the last two symbols (RFC 4648 section 4)
+    Standard,
+    /// `-` and `_` for the last two symbols (RFC 4648 section 5)
+    UrlSafe,
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Padding {
+    /// output is padded with `=` and padding is required when decoding
+    Padded,
+    /// output has no padding and `=` is rejected when decoding
+    Unpadded,
+}
+
+/// Alphabet, padding and line wrapping used by `Base64Decoder`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Base64Engine {
+    encode: [u8; 64],
+    decode: [u8; 256],
+    padding: Padding,
+    line_width: Option<usize>,
+}
+
+impl Base64Engine {
+    pub const STANDARD: Base64Engine =
+        Base64Engine::new(Alphabet::Standard, Padding::Padded, false);
+    pub const URL_SAFE: Base64Engine = Base64Engine::new(Alphabet::UrlSafe, Padding::Padded, false);
+    pub const URL_SAFE_NO_PAD: Base64Engine =
+        Base64Engine::new(Alphabet::UrlSafe, Padding::Unpadded, false);
+    pub const MIME: Base64Engine = Base64Engine::new(Alphabet::Standard, Padding::Padded, true);
+
+    /// `mime` wraps the output with `\r\n` every 76 characters and skips line breaks when decoding
+    pub const fn new(alphabet: Alphabet, padding: Padding, mime: bool) -> Self {
+        let encode = match alphabet {
+            Alphabet::Standard => BASE64_TABLE,
+            Alphabet::UrlSafe => BASE64_URL_TABLE,
+        };
+        Self {
+            encode,
+            decode: decode_table(&encode),
+            padding,
+            line_width: if mime { Some(MIME_LINE_WIDTH) } else { None },
+        }
+    }
+
+    /// number of characters `len` bytes encode to, line breaks included
+    pub fn encoded_len(&self, len: usize) -> usize {
+        let chars = match self.padding {
+            Padding::Padded => len.div_ceil(3) * 4,
+            Padding::Unpadded => (len * 4).div_ceil(3),
+        };
+        match self.line_width {
+            Some(width) if chars > 0 => chars + (chars - 1) / width * 2,
+            _ => chars,
+        }
+    }
+
+    fn is_line_break(&self, byte: u8) -> bool {
+        self.line_width.is_some() && (byte == b'\r' || byte == b'\n')
+    }
+}
+
+impl Default for Base64Engine {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+impl Codec for Base64Engine {
+    fn encoded_len(&self, len: usize) -> usize {
+        Base64Engine::encoded_len(self, len)
+    }
+
+    fn encode_into(&self, bytes: &[u8], out: &mut Vec<u8>) {
+        let full = bytes.len() - bytes.len() % 3;
+        let start = out.len();
+        out.reserve(self.encoded_len(bytes.len()));
+        encode_blocks(self, &bytes[..full], out);
+        encode_tail(self, &bytes[full..], out);
+        if let Some(width) = self.line_width {
+            wrap_lines(out, start, width, 0);
+        }
+    }
+
+    fn decode_into(&self, text: &[u8], out: &mut Vec<u8>) -> Result<(), Base64Error> {
+        let mut state = DecodeState::default();
+        state.push(self, text, out)?;
+        state.finish(self, out)
+    }
+}
+
+wasm_codec!(Base64Decoder, Base64Engine, to_base64, from_base64);
+
+#[wasm_bindgen]
+impl Base64Decoder {
+    pub fn new() -> Self {
+        Self::with_codec(Base64Engine::STANDARD)
+    }
+
+    pub fn with_config(alphabet: Alphabet, padding: Padding, mime: bool) -> Self {
+        Self::with_codec(Base64Engine::new(alphabet, padding, mime))
+    }
+}
+
+//encodes whole 3 byte groups, `bytes.len()` must be a multiple of 3
+fn encode_blocks(engine: &Base64Engine, bytes: &[u8], out: &mut Vec<u8>) {
+    let start = out.len();
+    out.resize(start + bytes.len() / 3 * 4, 0);
+    let done = simd::encode_blocks(engine, bytes, &mut out[start..]);
+    encode_blocks_scalar(engine, &bytes[done..], &mut out[start + done / 3 * 4..]);
+}
+
+fn encode_blocks_scalar(engine: &Base64Engine, bytes: &[u8], out: &mut [u8]) {
+    let table = &engine.encode;
+    bytes
+        .chunks_exact(3)
+        .zip(out.chunks_exact_mut(4))
+        .for_each(|(chunk_bytes, chunk_base64)| {
+            let byte1 = chunk_bytes[0] as usize;
+            let byte2 = chunk_bytes[1] as usize;
+            let byte3 = chunk_bytes[2] as usize;
+            let bits24 = (byte1 << 16) | (byte2 << 8) | byte3;
+
+            let a1 = bits24 >> 18;
+            let a2 = (bits24 >> 12) & 0b111111;
+            let a3 = (bits24 >> 6) & 0b111111;
+            let a4 = bits24 & 0b111111;
+
+            chunk_base64[0] = table[a1];
+            chunk_base64[1] = table[a2];
+            chunk_base64[2] = table[a3];
+            chunk_base64[3] = table[a4];
+        });
+}
+
+//encodes the last 0 to 2 bytes of the input, padding them if the engine asks for it
+fn encode_tail(engine: &Base64Engine, tail: &[u8], out: &mut Vec<u8>) {
+    if tail.is_empty() {
+        return;
+    }
+    let mut block = [0u8; 3];
+    block[..tail.len()].copy_from_slice(tail);
+    let start = out.len();
+    encode_blocks(engine, &block, out);
+    let padding = 3 - tail.len();
+    match engine.padding {
+        Padding::Padded => out[start + 4 - padding..].fill(b'='),
+        Padding::Unpadded => out.truncate(start + 4 - padding),
+    }
+}
+
+/// Decoding progress that survives between chunks of input:
+/// the 0 to 3 symbols of an incomplete group and the padding seen so far
+#[derive(Debug, Clone, Default)]
+struct DecodeState {
+    quad: [u8; 4],
+    filled: usize,
+    symbols: usize,
+    consumed: usize,
+    last_index: usize,
+    padding: usize,
+    padding_start: usize,
+}
+
+impl DecodeState {
+    fn push(
+        &mut self,
+        engine: &Base64Engine,
+        text: &[u8],
+        out: &mut Vec<u8>,
+    ) -> Result<(), Base64Error> {
+        self.push_with(engine, text, out, simd::decode_blocks)
+    }
+
+    //`kernel` decodes whole groups in bulk while the state is at a group boundary
+    fn push_with(
+        &mut self,
+        engine: &Base64Engine,
+        text: &[u8],
+        out: &mut Vec<u8>,
+        kernel: fn(&Base64Engine, &[u8], &mut Vec<u8>) -> usize,
+    ) -> Result<(), Base64Error> {
+        out.reserve(text.len() / 4 * 3);
+        let offset = self.consumed;
+        self.consumed += text.len();
+        let mut i = 0;
+        while i < text.len() {
+            if self.filled == 0 && self.padding == 0 {
+                let decoded = kernel(engine, &text[i..], out);
+                if decoded > 0 {
+                    i += decoded;
+                    self.symbols += decoded;
+                    self.last_index = offset + i - 1;
+                    continue;
+                }
+            }
+            let byte = text[i];
+            let index = offset + i;
+            i += 1;
+            if engine.is_line_break(byte) {
+                continue;
+            }
+            if byte == b'=' && engine.padding == Padding::Padded && self.filled >= 2 {
+                if self.padding == 0 {
+                    self.padding_start = index;
+                }
+                self.padding += 1;
+                if self.filled + self.padding > 4 {
+                    return Err(Base64Error::InvalidPadding(index));
+                }
+                continue;
+            }
+            if self.padding > 0 {
+                return Err(Base64Error::InvalidPadding(self.padding_start));
+            }
+            self.quad[self.filled] = decode_symbol(&engine.decode, byte, index)?;
+            self.filled += 1;
+            self.symbols += 1;
+            self.last_index = index;
+            if self.filled == 4 {
+                let [_, b1, b2, b3] = pack_quad(&self.quad, 4).to_be_bytes();
+                out.extend_from_slice(&[b1, b2, b3]);
+                self.filled = 0;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, engine: &Base64Engine, out: &mut Vec<u8>) -> Result<(), Base64Error> {
+        let state = std::mem::take(self);
+        let total = state.symbols + state.padding;
+        if engine.padding == Padding::Padded && !total.is_multiple_of(4) {
+            return Err(Base64Error::InvalidLength(total));
+        }
+        let [_, b1, b2, b3] = pack_quad(&state.quad, state.filled).to_be_bytes();
+        match state.filled {
+            0 => {}
+            3 if b3 == 0 => out.extend_from_slice(&[b1, b2]),
+            2 if b2 == 0 && b3 == 0 => out.push(b1),
+            1 => return Err(Base64Error::InvalidLength(state.symbols)),
+            _ => return Err(Base64Error::TrailingBits(state.last_index)),
+        }
+        Ok(())
+    }
+}
+
+fn pack_quad(quad: &[u8; 4], filled: usize) -> u32 {
+    quad[..filled]
+        .iter()
+        .enumerate()
+        .fold(0, |acc, (i, &value)| acc | (value as u32) << (18 - 6 * i))
+}
+
+//inserts \r\n every `width` characters of `buffer[start..]`, moving the lines from the back so it
+//can be done in place. `column` is how many characters the current line already had before `start`,
+//from 0 to `width`, and the column at the end is returned so the next chunk can continue the same line
+fn wrap_lines(buffer: &mut Vec<u8>, start: usize, width: usize, column: usize) -> usize {
+    let len = buffer.len() - start;
+    if len == 0 {
+        return column;
+    }
+    let next_column = (column + len - 1) % width + 1;
+    let first = width - column;
+    if len <= first {
+        return next_column;
+    }
+    let breaks = (len - 1 - first) / width + 1;
+    buffer.resize(buffer.len() + breaks * 2, 0);
+    let mut src_end = start + len;
+    let mut dst_end = buffer.len();
+    for line in (0..breaks).rev() {
+        let src_start = start + first + line * width;
+        let line_len = src_end - src_start;
+        buffer.copy_within(src_start..src_end, dst_end - line_len);
+        dst_end -= line_len + 2;
+        buffer[dst_end..dst_end + 2].copy_from_slice(b"\r\n");
+        src_end = src_start;
+    }
+    next_column
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Alphabet, Base64Decoder, Base64Engine, Base64Error, Padding};
+
+    #[test]
+    fn base64_basic() {
+        let bytes = b"Hello, world!";
+        let mut decoder = super::Base64Decoder::new();
+        decoder.to_base64(bytes);
+        assert_eq!(decoder.bytes, b"SGVsbG8sIHdvcmxkIQ==");
+    }
+
+    #[test]
+    fn decode_round_trip() {
+        let mut decoder = Base64Decoder::new();
+        for len in 0..64 {
+            let bytes: Vec<u8> = (0..len).map(|i| (i * 37 + 11) as u8).collect();
+            decoder.to_base64(&bytes);
+            let encoded = decoder.bytes.clone();
+            decoder.decode(&encoded).unwrap();
+            assert_eq!(decoder.bytes, bytes);
+        }
+    }
+
+    #[test]
+    fn decode_errors() {
+        let mut decoder = Base64Decoder::new();
+        let mut decode = |text: &[u8]| decoder.decode(text).map(|_| ());
+        assert_eq!(decode(b"SGVsbG8"), Err(Base64Error::InvalidLength(7)));
+        assert_eq!(
+            decode(b"SGV*bG8="),
+            Err(Base64Error::InvalidSymbol {
+                index: 3,
+                byte: b'*'
+            })
+        );
+        assert_eq!(decode(b"SG=sbG8="), Err(Base64Error::InvalidPadding(2)));
+        assert_eq!(decode(b"S==="), Err(Base64Error::InvalidPadding(1)));
+        assert_eq!(decode(b"SGVsbG9="), Err(Base64Error::TrailingBits(6)));
+        assert_eq!(decode(b"SR=="), Err(Base64Error::TrailingBits(1)));
+    }
+
+    #[test]
+    fn url_safe_unpadded() {
+        let mut decoder = Base64Decoder::with_config(Alphabet::UrlSafe, Padding::Unpadded, false);
+        decoder.to_base64(&[0xfb, 0xff]);
+        assert_eq!(decoder.bytes, b"-_8");
+        decoder.decode(b"-_8").unwrap();
+        assert_eq!(decoder.bytes, [0xfb, 0xff]);
+        assert_eq!(decoder.decode(b"-_8="), Err(Base64Error::InvalidPadding(3)));
+        assert_eq!(
+            decoder.decode(b"+/8"),
+            Err(Base64Error::InvalidSymbol {
+                index: 0,
+                byte: b'+'
+            })
+        );
+        assert_eq!(decoder.decode(b"-_8-_"), Err(Base64Error::InvalidLength(5)));
+
+        let mut standard = Base64Decoder::new();
+        standard.to_base64(&[0xfb, 0xff]);
+        assert_eq!(standard.bytes, b"+/8=");
+    }
+
+    #[test]
+    fn engines_round_trip() {
+        let engines = [
+            Base64Engine::STANDARD,
+            Base64Engine::URL_SAFE,
+            Base64Engine::URL_SAFE_NO_PAD,
+            Base64Engine::MIME,
+        ];
+        for engine in engines {
+            let mut decoder = Base64Decoder::with_codec(engine);
+            for len in [0, 1, 2, 3, 56, 57, 58, 114, 200] {
+                let bytes: Vec<u8> = (0..len).map(|i| (i * 91 + 7) as u8).collect();
+                decoder.to_base64(&bytes);
+                assert_eq!(decoder.bytes.len(), engine.encoded_len(len));
+                let encoded = decoder.bytes.clone();
+                decoder.decode(&encoded).unwrap();
+                assert_eq!(decoder.bytes, bytes);
+            }
+        }
+    }
+
+    #[test]
+    fn mime_wraps_lines() {
+        let bytes = [0u8; 120];
+        let mut mime = Base64Decoder::with_codec(Base64Engine::MIME);
+        mime.to_base64(&bytes);
+        let lines: Vec<&[u8]> = mime.bytes.split(|&c| c == b'\n').collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].len(), 77);
+        assert_eq!(lines[1].len(), 77);
+        assert!(lines[..2].iter().all(|line| line.ends_with(b"\r")));
+        assert_eq!(lines[2], b"AAAAAAAA");
+
+        let mut standard = Base64Decoder::new();
+        standard.to_base64(&bytes);
+        let unwrapped: Vec<u8> = mime
+            .bytes
+            .iter()
+            .copied()
+            .filter(|c| !c.is_ascii_whitespace())
+            .collect();
+        assert_eq!(unwrapped, standard.bytes);
+        assert_eq!(
+            standard.decode(&mime.bytes),
+            Err(Base64Error::InvalidSymbol {
+                index: 76,
+                byte: b'\r'
+            })
+        );
+    }
+}
@@ -0,0 +1,405 @@
+//! Vectorized base64 kernels, 12 bytes <-> 16 characters per step.
+//! The algorithm is written once over `Lanes` and implemented for
+//! x86 `ssse3`, picked when the cpu has it, and wasm `simd128`, used when the
+//! module is compiled with `-C target-feature=+simd128`. Otherwise the
+//! kernels consume nothing and the scalar code does all the work.
+
+#[cfg(any(
+    target_arch = "x86_64",
+    all(target_arch = "wasm32", target_feature = "simd128")
+))]
+pub(super) use kernels::{decode_blocks, encode_blocks};
+
+#[cfg(not(any(
+    target_arch = "x86_64",
+    all(target_arch = "wasm32", target_feature = "simd128")
+)))]
+pub(super) fn encode_blocks(_: &super::Base64Engine, _: &[u8], _: &mut [u8]) -> usize {
+    0
+}
+
+#[cfg(not(any(
+    target_arch = "x86_64",
+    all(target_arch = "wasm32", target_feature = "simd128")
+)))]
+pub(super) fn decode_blocks(_: &super::Base64Engine, _: &[u8], _: &mut Vec<u8>) -> usize {
+    0
+}
+
+#[cfg(any(
+    target_arch = "x86_64",
+    all(target_arch = "wasm32", target_feature = "simd128")
+))]
+mod kernels {
+    use super::super::Base64Engine;
+
+    /// Encodes as many 12 byte groups as possible into `out`, which must have
+    /// room for `input.len() / 3 * 4` characters. Returns the number of input bytes consumed.
+    pub fn encode_blocks(engine: &Base64Engine, input: &[u8], out: &mut [u8]) -> usize {
+        if !arch::supported() {
+            return 0;
+        }
+        unsafe { arch::encode(engine, input, out) }
+    }
+
+    /// Decodes 16 character groups while they only contain alphabet symbols.
+    /// Returns the number of characters consumed, anything else
+    /// (padding, line breaks, errors) is left for the scalar decoder.
+    pub fn decode_blocks(engine: &Base64Engine, input: &[u8], out: &mut Vec<u8>) -> usize {
+        if !arch::supported() {
+            return 0;
+        }
+        unsafe { arch::decode(engine, input, out) }
+    }
+
+    trait Lanes: Copy {
+        fn load(bytes: &[u8; 16]) -> Self;
+        fn store(self) -> [u8; 16];
+        fn splat8(value: u8) -> Self;
+        fn splat16(value: u16) -> Self;
+        fn splat32(value: u32) -> Self;
+        fn and(self, other: Self) -> Self;
+        fn or(self, other: Self) -> Self;
+        fn add8(self, other: Self) -> Self;
+        fn sub_sat_u8(self, other: Self) -> Self;
+        fn gt_i8(self, other: Self) -> Self;
+        fn eq8(self, other: Self) -> Self;
+        fn shl16<const N: i32>(self) -> Self;
+        fn shr16<const N: i32>(self) -> Self;
+        fn shl32<const N: i32>(self) -> Self;
+        fn shr32<const N: i32>(self) -> Self;
+        /// picks bytes of `self` by the indices in `index`, indices >= 0x80 give 0
+        fn shuffle(self, index: Self) -> Self;
+        fn all_set(self) -> bool;
+    }
+
+    //inlined into the `target_feature` entry points of `arch`, like the `Lanes` methods
+    #[inline(always)]
+    fn encode<V: Lanes>(engine: &Base64Engine, input: &[u8], out: &mut [u8]) -> usize {
+        let spread = V::load(&[1, 0, 2, 1, 4, 3, 5, 4, 7, 6, 8, 7, 10, 9, 11, 10]);
+        //offset added to each 6 bit value, picked by the range it falls in
+        let mut offsets = [0u8; 16];
+        offsets[0] = b'a'.wrapping_sub(26);
+        offsets[1..11].fill(b'0'.wrapping_sub(52));
+        offsets[11] = engine.encode[62].wrapping_sub(62);
+        offsets[12] = engine.encode[63].wrapping_sub(63);
+        offsets[13] = b'A';
+        let offsets = V::load(&offsets);
+
+        let mut done = 0;
+        while input.len() - done >= 16 {
+            let block = V::load(input[done..done + 16].try_into().unwrap());
+            //each 32 bit lane now holds [b1, b0, b2, b1]
+            let x = block.shuffle(spread);
+            let a = x.shr16::<10>().and(V::splat32(0x0000_003f));
+            let b = x.shl16::<4>().and(V::splat32(0x0000_3f00));
+            let c = x.shr16::<6>().and(V::splat32(0x003f_0000));
+            let d = x.shl16::<8>().and(V::splat32(0x3f00_0000));
+            let sextets = a.or(b).or(c).or(d);
+
+            let range = sextets
+                .sub_sat_u8(V::splat8(51))
+                .or(V::splat8(26).gt_i8(sextets).and(V::splat8(13)));
+            let chars = offsets.shuffle(range).add8(sextets);
+            let at = done / 3 * 4;
+            out[at..at + 16].copy_from_slice(&chars.store());
+            done += 12;
+        }
+        done
+    }
+
+    #[inline(always)]
+    fn decode<V: Lanes>(engine: &Base64Engine, input: &[u8], out: &mut Vec<u8>) -> usize {
+        let in_range =
+            |c: V, from: u8, to: u8| c.gt_i8(V::splat8(from - 1)).and(V::splat8(to + 1).gt_i8(c));
+        let (c62, c63) = (engine.encode[62], engine.encode[63]);
+        let gather = V::load(&[
+            2, 1, 0, 6, 5, 4, 10, 9, 8, 14, 13, 12, 0x80, 0x80, 0x80, 0x80,
+        ]);
+
+        let mut done = 0;
+        while input.len() - done >= 16 {
+            let c = V::load(input[done..done + 16].try_into().unwrap());
+            let upper = in_range(c, b'A', b'Z');
+            let lower = in_range(c, b'a', b'z');
+            let digit = in_range(c, b'0', b'9');
+            let is62 = c.eq8(V::splat8(c62));
+            let is63 = c.eq8(V::splat8(c63));
+            if !upper.or(lower).or(digit).or(is62).or(is63).all_set() {
+                break;
+            }
+            let offset = upper
+                .and(V::splat8(0u8.wrapping_sub(b'A')))
+                .or(lower.and(V::splat8(26u8.wrapping_sub(b'a'))))
+                .or(digit.and(V::splat8(52u8.wrapping_sub(b'0'))))
+                .or(is62.and(V::splat8(62u8.wrapping_sub(c62))))
+                .or(is63.and(V::splat8(63u8.wrapping_sub(c63))));
+            let sextets = c.add8(offset);
+
+            //join pairs of sextets into 12 bits, then pairs of those into 24 bits
+            let x = sextets
+                .and(V::splat16(0x003f))
+                .shl16::<6>()
+                .or(sextets.shr16::<8>());
+            let x = x
+                .and(V::splat32(0x0000_ffff))
+                .shl32::<12>()
+                .or(x.shr32::<16>());
+            out.extend_from_slice(&x.shuffle(gather).store()[..12]);
+            done += 16;
+        }
+        done
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    mod arch {
+        use super::{Base64Engine, Lanes};
+        use core::arch::wasm32::*;
+
+        //wasm can't detect features, a module built with simd128 only loads on engines with it
+        pub fn supported() -> bool {
+            true
+        }
+
+        /// # Safety
+        /// the engine must support simd128
+        #[target_feature(enable = "simd128")]
+        pub unsafe fn encode(engine: &Base64Engine, input: &[u8], out: &mut [u8]) -> usize {
+            super::encode::<V128>(engine, input, out)
+        }
+
+        /// # Safety
+        /// the engine must support simd128
+        #[target_feature(enable = "simd128")]
+        pub unsafe fn decode(engine: &Base64Engine, input: &[u8], out: &mut Vec<u8>) -> usize {
+            super::decode::<V128>(engine, input, out)
+        }
+
+        #[derive(Clone, Copy)]
+        pub struct V128(v128);
+
+        impl Lanes for V128 {
+            #[inline(always)]
+            fn load(bytes: &[u8; 16]) -> Self {
+                V128(unsafe { v128_load(bytes.as_ptr() as *const v128) })
+            }
+            #[inline(always)]
+            fn store(self) -> [u8; 16] {
+                let mut bytes = [0u8; 16];
+                unsafe { v128_store(bytes.as_mut_ptr() as *mut v128, self.0) };
+                bytes
+            }
+            #[inline(always)]
+            fn splat8(value: u8) -> Self {
+                V128(u8x16_splat(value))
+            }
+            #[inline(always)]
+            fn splat16(value: u16) -> Self {
+                V128(u16x8_splat(value))
+            }
+            #[inline(always)]
+            fn splat32(value: u32) -> Self {
+                V128(u32x4_splat(value))
+            }
+            #[inline(always)]
+            fn and(self, other: Self) -> Self {
+                V128(v128_and(self.0, other.0))
+            }
+            #[inline(always)]
+            fn or(self, other: Self) -> Self {
+                V128(v128_or(self.0, other.0))
+            }
+            #[inline(always)]
+            fn add8(self, other: Self) -> Self {
+                V128(u8x16_add(self.0, other.0))
+            }
+            #[inline(always)]
+            fn sub_sat_u8(self, other: Self) -> Self {
+                V128(u8x16_sub_sat(self.0, other.0))
+            }
+            #[inline(always)]
+            fn gt_i8(self, other: Self) -> Self {
+                V128(i8x16_gt(self.0, other.0))
+            }
+            #[inline(always)]
+            fn eq8(self, other: Self) -> Self {
+                V128(u8x16_eq(self.0, other.0))
+            }
+            #[inline(always)]
+            fn shl16<const N: i32>(self) -> Self {
+                V128(u16x8_shl(self.0, N as u32))
+            }
+            #[inline(always)]
+            fn shr16<const N: i32>(self) -> Self {
+                V128(u16x8_shr(self.0, N as u32))
+            }
+            #[inline(always)]
+            fn shl32<const N: i32>(self) -> Self {
+                V128(u32x4_shl(self.0, N as u32))
+            }
+            #[inline(always)]
+            fn shr32<const N: i32>(self) -> Self {
+                V128(u32x4_shr(self.0, N as u32))
+            }
+            #[inline(always)]
+            fn shuffle(self, index: Self) -> Self {
+                V128(u8x16_swizzle(self.0, index.0))
+            }
+            #[inline(always)]
+            fn all_set(self) -> bool {
+                u8x16_all_true(self.0)
+            }
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    mod arch {
+        use super::{Base64Engine, Lanes};
+        use core::arch::x86_64::*;
+
+        pub fn supported() -> bool {
+            is_x86_feature_detected!("ssse3")
+        }
+
+        /// # Safety
+        /// the cpu must support ssse3
+        #[target_feature(enable = "ssse3")]
+        pub unsafe fn encode(engine: &Base64Engine, input: &[u8], out: &mut [u8]) -> usize {
+            super::encode::<V128>(engine, input, out)
+        }
+
+        /// # Safety
+        /// the cpu must support ssse3
+        #[target_feature(enable = "ssse3")]
+        pub unsafe fn decode(engine: &Base64Engine, input: &[u8], out: &mut Vec<u8>) -> usize {
+            super::decode::<V128>(engine, input, out)
+        }
+
+        #[derive(Clone, Copy)]
+        pub struct V128(__m128i);
+
+        impl Lanes for V128 {
+            #[inline(always)]
+            fn load(bytes: &[u8; 16]) -> Self {
+                V128(unsafe { _mm_loadu_si128(bytes.as_ptr() as *const __m128i) })
+            }
+            #[inline(always)]
+            fn store(self) -> [u8; 16] {
+                let mut bytes = [0u8; 16];
+                unsafe { _mm_storeu_si128(bytes.as_mut_ptr() as *mut __m128i, self.0) };
+                bytes
+            }
+            #[inline(always)]
+            fn splat8(value: u8) -> Self {
+                V128(unsafe { _mm_set1_epi8(value as i8) })
+            }
+            #[inline(always)]
+            fn splat16(value: u16) -> Self {
+                V128(unsafe { _mm_set1_epi16(value as i16) })
+            }
+            #[inline(always)]
+            fn splat32(value: u32) -> Self {
+                V128(unsafe { _mm_set1_epi32(value as i32) })
+            }
+            #[inline(always)]
+            fn and(self, other: Self) -> Self {
+                V128(unsafe { _mm_and_si128(self.0, other.0) })
+            }
+            #[inline(always)]
+            fn or(self, other: Self) -> Self {
+                V128(unsafe { _mm_or_si128(self.0, other.0) })
+            }
+            #[inline(always)]
+            fn add8(self, other: Self) -> Self {
+                V128(unsafe { _mm_add_epi8(self.0, other.0) })
+            }
+            #[inline(always)]
+            fn sub_sat_u8(self, other: Self) -> Self {
+                V128(unsafe { _mm_subs_epu8(self.0, other.0) })
+            }
+            #[inline(always)]
+            fn gt_i8(self, other: Self) -> Self {
+                V128(unsafe { _mm_cmpgt_epi8(self.0, other.0) })
+            }
+            #[inline(always)]
+            fn eq8(self, other: Self) -> Self {
+                V128(unsafe { _mm_cmpeq_epi8(self.0, other.0) })
+            }
+            #[inline(always)]
+            fn shl16<const N: i32>(self) -> Self {
+                V128(unsafe { _mm_slli_epi16::<N>(self.0) })
+            }
+            #[inline(always)]
+            fn shr16<const N: i32>(self) -> Self {
+                V128(unsafe { _mm_srli_epi16::<N>(self.0) })
+            }
+            #[inline(always)]
+            fn shl32<const N: i32>(self) -> Self {
+                V128(unsafe { _mm_slli_epi32::<N>(self.0) })
+            }
+            #[inline(always)]
+            fn shr32<const N: i32>(self) -> Self {
+                V128(unsafe { _mm_srli_epi32::<N>(self.0) })
+            }
+            #[inline(always)]
+            fn shuffle(self, index: Self) -> Self {
+                V128(unsafe { _mm_shuffle_epi8(self.0, index.0) })
+            }
+            #[inline(always)]
+            fn all_set(self) -> bool {
+                unsafe { _mm_movemask_epi8(self.0) == 0xffff }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::base64::{encode_blocks, encode_blocks_scalar, Base64Engine, DecodeState};
+    use crate::rng::SeededRng;
+
+    fn random_bytes(rng: &mut SeededRng, len: usize) -> Vec<u8> {
+        (0..len).map(|_| rng.next_u64() as u8).collect()
+    }
+
+    fn decode_with(
+        engine: &Base64Engine,
+        text: &[u8],
+        kernel: fn(&Base64Engine, &[u8], &mut Vec<u8>) -> usize,
+    ) -> (Result<(), crate::base64::Base64Error>, Vec<u8>) {
+        let mut out = vec![];
+        let mut state = DecodeState::default();
+        let result = state
+            .push_with(engine, text, &mut out, kernel)
+            .and_then(|_| state.finish(engine, &mut out));
+        (result, out)
+    }
+
+    #[test]
+    fn vector_matches_scalar() {
+        let scalar = |_: &Base64Engine, _: &[u8], _: &mut Vec<u8>| 0;
+        let mut rng = SeededRng::new(4);
+        for engine in [Base64Engine::STANDARD, Base64Engine::URL_SAFE_NO_PAD] {
+            for _ in 0..200 {
+                let len = (rng.next_f64() * 300.0) as usize;
+                let bytes = random_bytes(&mut rng, len - len % 3);
+
+                let mut vector = vec![];
+                encode_blocks(&engine, &bytes, &mut vector);
+                let mut reference = vec![0; bytes.len() / 3 * 4];
+                encode_blocks_scalar(&engine, &bytes, &mut reference);
+                assert_eq!(vector, reference);
+
+                let mut text = vector;
+                if !text.is_empty() && rng.next_f64() < 0.5 {
+                    let at = (rng.next_f64() * text.len() as f64) as usize;
+                    text[at] = rng.next_u64() as u8;
+                }
+                assert_eq!(
+                    decode_with(&engine, &text, super::decode_blocks),
+                    decode_with(&engine, &text, scalar)
+                );
+            }
+        }
+    }
+}
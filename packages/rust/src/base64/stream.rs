@@ -0,0 +1,252 @@
+use super::{
+    encode_blocks, encode_tail, wrap_lines, Alphabet, Base64Engine, Base64Error, DecodeState,
+    Padding,
+};
+use crate::output::{Generation, OutputView};
+use wasm_bindgen::prelude::*;
+
+/// Encodes input that arrives in several chunks.
+/// `push` keeps the 0 to 2 bytes that don't complete a group for the next call
+/// and `finish` writes them along with the padding.
+/// The output of each call replaces the previous one, so JS only reads the new part.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct Base64StreamEncoder {
+    engine: Base64Engine,
+    leftover: [u8; 3],
+    leftover_len: usize,
+    column: usize,
+    output: Vec<u8>,
+    generation: Generation,
+}
+
+#[wasm_bindgen]
+impl Base64StreamEncoder {
+    pub fn new() -> Self {
+        Self::with_engine(Base64Engine::STANDARD)
+    }
+
+    pub fn with_config(alphabet: Alphabet, padding: Padding, mime: bool) -> Self {
+        Self::with_engine(Base64Engine::new(alphabet, padding, mime))
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) -> OutputView {
+        self.encode_chunk(bytes);
+        self.generation.track(&self.output)
+    }
+
+    pub fn finish(&mut self) -> OutputView {
+        self.encode_end();
+        self.generation.track(&self.output)
+    }
+
+    /// the output of the last `push` or `finish` call
+    pub fn output_view(&self) -> OutputView {
+        self.generation.view(&self.output)
+    }
+
+    /// number of bytes written by the last `push` or `finish` call
+    pub fn output_len(&self) -> usize {
+        self.output.len()
+    }
+}
+
+impl Base64StreamEncoder {
+    pub fn with_engine(engine: Base64Engine) -> Self {
+        Self {
+            engine,
+            ..Default::default()
+        }
+    }
+
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    pub fn encode_chunk(&mut self, mut bytes: &[u8]) {
+        self.output.clear();
+        if self.leftover_len > 0 {
+            let take = (3 - self.leftover_len).min(bytes.len());
+            self.leftover[self.leftover_len..self.leftover_len + take]
+                .copy_from_slice(&bytes[..take]);
+            self.leftover_len += take;
+            bytes = &bytes[take..];
+            if self.leftover_len < 3 {
+                return;
+            }
+            encode_blocks(&self.engine, &self.leftover, &mut self.output);
+            self.leftover_len = 0;
+        }
+        let full = bytes.len() - bytes.len() % 3;
+        encode_blocks(&self.engine, &bytes[..full], &mut self.output);
+        self.leftover_len = bytes.len() - full;
+        self.leftover[..self.leftover_len].copy_from_slice(&bytes[full..]);
+        self.wrap();
+    }
+
+    pub fn encode_end(&mut self) {
+        self.output.clear();
+        encode_tail(
+            &self.engine,
+            &self.leftover[..self.leftover_len],
+            &mut self.output,
+        );
+        self.wrap();
+        self.leftover_len = 0;
+        self.column = 0;
+    }
+
+    fn wrap(&mut self) {
+        if let Some(width) = self.engine.line_width {
+            self.column = wrap_lines(&mut self.output, 0, width, self.column);
+        }
+    }
+}
+
+/// Decodes base64 text that arrives in several chunks.
+/// `push` keeps the 1 to 3 symbols that don't complete a group for the next call
+/// and `finish` checks the padding and flushes them.
+/// Error indices count from the start of the stream, and after an error
+/// the stream starts over on the next `push`.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct Base64StreamDecoder {
+    engine: Base64Engine,
+    state: DecodeState,
+    output: Vec<u8>,
+    generation: Generation,
+}
+
+#[wasm_bindgen]
+impl Base64StreamDecoder {
+    pub fn new() -> Self {
+        Self::with_engine(Base64Engine::STANDARD)
+    }
+
+    pub fn with_config(alphabet: Alphabet, padding: Padding, mime: bool) -> Self {
+        Self::with_engine(Base64Engine::new(alphabet, padding, mime))
+    }
+
+    pub fn push(&mut self, text: &[u8]) -> Result<OutputView, JsError> {
+        self.decode_chunk(text)?;
+        Ok(self.generation.track(&self.output))
+    }
+
+    pub fn finish(&mut self) -> Result<OutputView, JsError> {
+        self.decode_end()?;
+        Ok(self.generation.track(&self.output))
+    }
+
+    /// the output of the last `push` or `finish` call
+    pub fn output_view(&self) -> OutputView {
+        self.generation.view(&self.output)
+    }
+
+    /// number of bytes written by the last `push` or `finish` call
+    pub fn output_len(&self) -> usize {
+        self.output.len()
+    }
+}
+
+impl Base64StreamDecoder {
+    pub fn with_engine(engine: Base64Engine) -> Self {
+        Self {
+            engine,
+            ..Default::default()
+        }
+    }
+
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    pub fn decode_chunk(&mut self, text: &[u8]) -> Result<(), Base64Error> {
+        self.output.clear();
+        let result = self.state.push(&self.engine, text, &mut self.output);
+        if result.is_err() {
+            self.state = DecodeState::default();
+        }
+        result
+    }
+
+    pub fn decode_end(&mut self) -> Result<(), Base64Error> {
+        self.output.clear();
+        self.state.finish(&self.engine, &mut self.output)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Base64StreamDecoder, Base64StreamEncoder};
+    use crate::base64::{Base64Decoder, Base64Engine, Base64Error};
+
+    fn sample(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 53 + 17) as u8).collect()
+    }
+
+    #[test]
+    fn encode_in_chunks_matches_whole() {
+        let bytes = sample(500);
+        for engine in [
+            Base64Engine::STANDARD,
+            Base64Engine::URL_SAFE_NO_PAD,
+            Base64Engine::MIME,
+        ] {
+            let mut whole = Base64Decoder::with_codec(engine);
+            whole.to_base64(&bytes);
+            for chunk_size in [1, 2, 3, 4, 7, 57, 76, 100] {
+                let mut stream = Base64StreamEncoder::with_engine(engine);
+                let mut encoded = vec![];
+                for chunk in bytes.chunks(chunk_size) {
+                    stream.encode_chunk(chunk);
+                    encoded.extend_from_slice(stream.output());
+                }
+                stream.encode_end();
+                encoded.extend_from_slice(stream.output());
+                assert_eq!(encoded, whole.bytes, "chunk size {}", chunk_size);
+            }
+        }
+    }
+
+    #[test]
+    fn decode_in_chunks_matches_whole() {
+        let bytes = sample(500);
+        for engine in [
+            Base64Engine::STANDARD,
+            Base64Engine::URL_SAFE_NO_PAD,
+            Base64Engine::MIME,
+        ] {
+            let mut whole = Base64Decoder::with_codec(engine);
+            whole.to_base64(&bytes);
+            for chunk_size in [1, 2, 3, 5, 77, 100] {
+                let mut stream = Base64StreamDecoder::with_engine(engine);
+                let mut decoded = vec![];
+                for chunk in whole.bytes.chunks(chunk_size) {
+                    stream.decode_chunk(chunk).unwrap();
+                    decoded.extend_from_slice(stream.output());
+                }
+                stream.decode_end().unwrap();
+                decoded.extend_from_slice(stream.output());
+                assert_eq!(decoded, bytes, "chunk size {}", chunk_size);
+            }
+        }
+    }
+
+    #[test]
+    fn decode_errors_use_stream_index() {
+        let mut stream = Base64StreamDecoder::new();
+        stream.decode_chunk(b"SGVs").unwrap();
+        assert_eq!(
+            stream.decode_chunk(b"bG*="),
+            Err(Base64Error::InvalidSymbol {
+                index: 6,
+                byte: b'*'
+            })
+        );
+        stream.decode_chunk(b"SGVsbG8").unwrap();
+        assert_eq!(stream.decode_end(), Err(Base64Error::InvalidLength(7)));
+        stream.decode_chunk(b"SGVsbG8=").unwrap();
+        stream.decode_end().unwrap();
+        assert_eq!(stream.output(), b"lo");
+    }
+}
@@ -1,3 +1,168 @@
-fn main() {
-    wasm::wordleMod::main();
+//! `wasm_bin` plays the wordle demo,
+//! `wasm_bin render <file.png|file.ppm> [--size WxH] [--region x0,x1,y0,y1] [--iterations N]`
+//! renders the Mandelbrot set to an image file
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use wasm::mandelbrot::{calc_set, export, FractalParams, RenderOptions};
+
+const USAGE: &str =
+    "usage: wasm_bin render <file.png|file.ppm> [--size WxH] [--region x0,x1,y0,y1] [--iterations N]";
+
+#[derive(Debug)]
+struct Render {
+    path: PathBuf,
+    width: u32,
+    heigth: u32,
+    region: Vec<f64>,
+    iterations: u32,
+}
+
+impl Render {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Render, String> {
+        let mut render = Render {
+            path: args.next().ok_or("missing output file")?.into(),
+            width: 800,
+            heigth: 600,
+            region: vec![-2.0, 1.0, -1.125, 1.125],
+            iterations: 200,
+        };
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("missing value for {}", flag))?;
+            let invalid = || format!("invalid value for {}: {}", flag, value);
+            match flag.as_str() {
+                "--size" => {
+                    let (width, heigth) = value.split_once('x').ok_or_else(invalid)?;
+                    render.width = width.parse().map_err(|_| invalid())?;
+                    render.heigth = heigth.parse().map_err(|_| invalid())?;
+                }
+                "--region" => {
+                    render.region = value
+                        .split(',')
+                        .map(|n| n.trim().parse::<f64>())
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| invalid())?;
+                    if render.region.len() != 4 {
+                        return Err(invalid());
+                    }
+                }
+                "--iterations" => render.iterations = value.parse().map_err(|_| invalid())?,
+                _ => return Err(format!("unknown option {}", flag)),
+            }
+        }
+        Ok(render)
+    }
+
+    fn rgba(&self) -> Vec<u8> {
+        let mut options = RenderOptions::new();
+        options.iterations = self.iterations;
+        let (width, heigth) = (self.width as usize, self.heigth as usize);
+        calc_set(
+            width,
+            heigth,
+            &self.region,
+            &FractalParams::default(),
+            &options,
+        )
+    }
+
+    fn run(&self) -> Result<(), String> {
+        export::save(&self.path, self.width, self.heigth, &self.rgba())
+            .map_err(|e| format!("{}: {}", self.path.display(), e))
+    }
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        None => {
+            wasm::wordleMod::main();
+            ExitCode::SUCCESS
+        }
+        Some("render") => match Render::parse(args).and_then(|render| render.run()) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(error) => {
+                eprintln!("{}\n{}", error, USAGE);
+                ExitCode::FAILURE
+            }
+        },
+        Some(command) => {
+            eprintln!("unknown command {}\n{}", command, USAGE);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Render;
+    use wasm::mandelbrot::export::{crc32, encode_png, encode_ppm};
+
+    fn parse(args: &[&str]) -> Result<Render, String> {
+        Render::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_the_options() {
+        let render = parse(&["set.png"]).unwrap();
+        assert_eq!(render.path.to_str(), Some("set.png"));
+        assert_eq!(
+            (render.width, render.heigth, render.iterations),
+            (800, 600, 200)
+        );
+        let render = parse(&[
+            "set.ppm",
+            "--size",
+            "30x20",
+            "--region",
+            "-1, 0,-0.5,0.5",
+            "--iterations",
+            "50",
+        ])
+        .unwrap();
+        assert_eq!(
+            (render.width, render.heigth, render.iterations),
+            (30, 20, 50)
+        );
+        assert_eq!(render.region, [-1.0, 0.0, -0.5, 0.5]);
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        for (args, error) in [
+            (&[][..], "missing output file"),
+            (&["a.png", "--zoom", "2"], "unknown option --zoom"),
+            (&["a.png", "--size"], "missing value for --size"),
+            (&["a.png", "--size", "30"], "invalid value for --size: 30"),
+            (
+                &["a.png", "--size", "30xa"],
+                "invalid value for --size: 30xa",
+            ),
+            (
+                &["a.png", "--region", "0,1,2"],
+                "invalid value for --region: 0,1,2",
+            ),
+            (
+                &["a.png", "--region", "0,1,x,3"],
+                "invalid value for --region: 0,1,x,3",
+            ),
+            (
+                &["a.png", "--iterations", "-5"],
+                "invalid value for --iterations: -5",
+            ),
+        ] {
+            assert_eq!(parse(args).unwrap_err(), error);
+        }
+    }
+
+    #[test]
+    fn golden_image() {
+        let render = parse(&["set.ppm", "--size", "24x16", "--iterations", "40"]).unwrap();
+        let rgba = render.rgba();
+        //checksums of the files, any change to the renderer or the encoders shows here
+        assert_eq!(crc32(&encode_ppm(24, 16, &rgba).unwrap()), 0x7085_91b6);
+        assert_eq!(crc32(&encode_png(24, 16, &rgba).unwrap()), 0x5acf_03e1);
+    }
 }
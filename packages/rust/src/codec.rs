@@ -0,0 +1,139 @@
+use std::fmt;
+
+/// A binary-to-text encoding: base64, base32, hex and Ascii85 all implement it
+pub trait Codec {
+    /// number of characters `len` bytes encode to, an upper bound for codecs that compress runs
+    fn encoded_len(&self, len: usize) -> usize;
+    /// appends the text encoding of `bytes` to `out`
+    fn encode_into(&self, bytes: &[u8], out: &mut Vec<u8>);
+    /// appends the bytes encoded in `text` to `out`
+    fn decode_into(&self, text: &[u8], out: &mut Vec<u8>) -> Result<(), DecodeError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// the number of symbols can't be produced by the encoder
+    InvalidLength(usize),
+    /// a byte outside the alphabet was found at the given index
+    InvalidSymbol { index: usize, byte: u8 },
+    /// padding is missing, too long or followed by more symbols
+    InvalidPadding(usize),
+    /// the last symbol carries bits that do not fit in the output
+    TrailingBits(usize),
+    /// the group ending at the given index encodes a value that doesn't fit in its bytes
+    Overflow(usize),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::InvalidLength(len) => write!(f, "invalid input length {}", len),
+            DecodeError::InvalidSymbol { index, byte } => {
+                write!(f, "invalid symbol 0x{:02x} at index {}", byte, index)
+            }
+            DecodeError::InvalidPadding(index) => write!(f, "invalid padding at index {}", index),
+            DecodeError::TrailingBits(index) => {
+                write!(f, "non zero trailing bits in symbol at index {}", index)
+            }
+            DecodeError::Overflow(index) => {
+                write!(f, "group ending at index {} overflows", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+pub(crate) const INVALID: u8 = 0xff;
+
+/// reverse lookup of an alphabet, `INVALID` for bytes outside of it
+pub(crate) const fn decode_table<const N: usize>(alphabet: &[u8; N]) -> [u8; 256] {
+    let mut table = [INVALID; 256];
+    let mut i = 0;
+    while i < N {
+        table[alphabet[i] as usize] = i as u8;
+        i += 1;
+    }
+    table
+}
+
+pub(crate) fn decode_symbol(table: &[u8; 256], byte: u8, index: usize) -> Result<u8, DecodeError> {
+    match table[byte as usize] {
+        INVALID if byte == b'=' => Err(DecodeError::InvalidPadding(index)),
+        INVALID => Err(DecodeError::InvalidSymbol { index, byte }),
+        value => Ok(value),
+    }
+}
+
+/// Declares a wasm object that owns an output buffer and encodes or decodes
+/// with a `Codec`, like `Base64Decoder` for base64
+macro_rules! wasm_codec {
+    ($name:ident, $codec:ty, $encode:ident, $decode:ident) => {
+        #[wasm_bindgen]
+        #[derive(Default)]
+        pub struct $name {
+            bytes: Vec<u8>,
+            codec: $codec,
+            generation: $crate::output::Generation,
+        }
+
+        #[wasm_bindgen]
+        impl $name {
+            pub fn $encode(&mut self, bytes: &[u8]) -> $crate::output::OutputView {
+                self.bytes.clear();
+                self.codec.encode_into(bytes, &mut self.bytes);
+                self.generation.track(&self.bytes)
+            }
+
+            pub fn $decode(&mut self, text: &[u8]) -> Result<$crate::output::OutputView, JsError> {
+                self.decode(text)?;
+                Ok(self.generation.track(&self.bytes))
+            }
+
+            /// the output of the last encode or decode call
+            pub fn output_view(&self) -> $crate::output::OutputView {
+                self.generation.view(&self.bytes)
+            }
+
+            /// number of bytes written by the last encode or decode call
+            pub fn output_len(&self) -> usize {
+                self.bytes.len()
+            }
+        }
+
+        impl $name {
+            pub fn with_codec(codec: $codec) -> Self {
+                Self {
+                    codec,
+                    ..Default::default()
+                }
+            }
+
+            pub fn output(&self) -> &[u8] {
+                &self.bytes
+            }
+
+            /// the decoding of the wasm method, keeping the error typed
+            pub fn decode(&mut self, text: &[u8]) -> Result<&[u8], $crate::codec::DecodeError> {
+                self.bytes.clear();
+                self.codec.decode_into(text, &mut self.bytes)?;
+                Ok(&self.bytes)
+            }
+        }
+    };
+}
+
+pub(crate) use wasm_codec;
+
+#[cfg(test)]
+pub(crate) fn assert_round_trip(codec: &impl Codec) {
+    for len in 0..70 {
+        let bytes: Vec<u8> = (0..len).map(|i| (i * 73 + 29) as u8).collect();
+        let mut text = vec![];
+        codec.encode_into(&bytes, &mut text);
+        assert!(text.len() <= codec.encoded_len(len), "length {}", len);
+        let mut decoded = vec![];
+        codec.decode_into(&text, &mut decoded).unwrap();
+        assert_eq!(decoded, bytes, "length {}", len);
+    }
+}
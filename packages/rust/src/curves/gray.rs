@@ -0,0 +1,34 @@
+use super::morton::{deinterleave, interleave};
+use super::SpaceFillingCurve;
+
+/// Faloutsos' Gray-code curve: the interleaved bits of a cell are the Gray code
+/// of its index, so neighbours along the curve differ in a single bit
+pub struct GrayCode;
+
+fn gray(d: i64) -> i64 {
+    d ^ (d >> 1)
+}
+
+fn gray_inverse(g: i64) -> i64 {
+    let mut d = g;
+    let mut shift = 1;
+    while shift < 64 {
+        d ^= d >> shift;
+        shift *= 2;
+    }
+    d
+}
+
+impl SpaceFillingCurve for GrayCode {
+    fn side(&self, n: i64) -> i64 {
+        (n as u64).next_power_of_two() as i64
+    }
+
+    fn index_to_point(&self, _: i64, d: i64) -> (i64, i64) {
+        deinterleave(gray(d))
+    }
+
+    fn point_to_index(&self, _: i64, x: i64, y: i64) -> i64 {
+        gray_inverse(interleave(x, y))
+    }
+}
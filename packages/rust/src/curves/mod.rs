@@ -0,0 +1,260 @@
+mod gray;
+mod moore;
+mod morton;
+mod peano;
+
+use crate::hilbert::Hilbert;
+use std::fmt;
+use wasm_bindgen::prelude::*;
+
+pub use gray::GrayCode;
+pub use moore::Moore;
+pub use morton::Morton;
+pub use peano::Peano;
+
+/// A bijection between positions along a curve and the cells of a square grid
+pub trait SpaceFillingCurve {
+    /// side of the smallest grid this curve can fill that covers `n` x `n`
+    fn side(&self, n: i64) -> i64;
+    fn index_to_point(&self, n: i64, d: i64) -> (i64, i64);
+    fn point_to_index(&self, n: i64, x: i64, y: i64) -> i64;
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurveKind {
+    Hilbert,
+    /// Morton / Z-order, bits of x and y interleaved
+    ZOrder,
+    /// works on 3^k grids
+    Peano,
+    /// closed loop made of four Hilbert curves
+    Moore,
+    GrayCode,
+}
+
+impl CurveKind {
+    pub fn curve(self) -> &'static dyn SpaceFillingCurve {
+        match self {
+            CurveKind::Hilbert => &Hilbert,
+            CurveKind::ZOrder => &Morton,
+            CurveKind::Peano => &Peano,
+            CurveKind::Moore => &Moore,
+            CurveKind::GrayCode => &GrayCode,
+        }
+    }
+}
+
+/// Input the 2D curve exports can't answer, their keys being 32 bit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyError {
+    /// the grid filled for this n has more cells than 32 bit keys
+    GridTooLarge(u32),
+    /// the point or key at this index is outside the grid
+    OutOfRange(usize),
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyError::GridTooLarge(n) => {
+                write!(
+                    f,
+                    "the cells of a grid covering {} don't fit in 32 bit keys",
+                    n
+                )
+            }
+            KeyError::OutOfRange(index) => write!(f, "entry {} is outside the grid", index),
+        }
+    }
+}
+
+impl std::error::Error for KeyError {}
+
+/// side of the grid `curve` fills for `n`, when all its cells have a 32 bit key
+pub fn key_side(curve: &dyn SpaceFillingCurve, n: u32) -> Result<i64, KeyError> {
+    let side = curve.side(n as i64);
+    match side * side <= 1 << 32 {
+        true => Ok(side),
+        false => Err(KeyError::GridTooLarge(n)),
+    }
+}
+
+/// curve index of each (x, y) pair in `points`, which must be inside the grid
+pub fn keys_in(
+    curve: &dyn SpaceFillingCurve,
+    n: u32,
+    points: &[i32],
+) -> Result<Vec<u32>, KeyError> {
+    let side = key_side(curve, n)?;
+    let inside = |c: i32| (0..side).contains(&(c as i64));
+    points
+        .chunks_exact(2)
+        .enumerate()
+        .map(|(i, p)| match inside(p[0]) && inside(p[1]) {
+            true => Ok(curve.point_to_index(n as i64, p[0] as i64, p[1] as i64) as u32),
+            false => Err(KeyError::OutOfRange(i)),
+        })
+        .collect()
+}
+
+/// (x, y) pairs of each curve index in `keys`, flattened, the keys must be inside the grid
+pub fn points_in(
+    curve: &dyn SpaceFillingCurve,
+    n: u32,
+    keys: &[u32],
+) -> Result<Vec<i32>, KeyError> {
+    let side = key_side(curve, n)?;
+    let mut points = Vec::with_capacity(keys.len() * 2);
+    for (i, &d) in keys.iter().enumerate() {
+        if d as i64 >= side * side {
+            return Err(KeyError::OutOfRange(i));
+        }
+        let (x, y) = curve.index_to_point(n as i64, d as i64);
+        points.extend([x as i32, y as i32]);
+    }
+    Ok(points)
+}
+
+pub fn all_points_in(
+    curve: &dyn SpaceFillingCurve,
+    n: u32,
+    amount: u32,
+) -> impl Iterator<Item = (i64, i64)> + '_ {
+    (0..amount as i64).map(move |d| curve.index_to_point(n as i64, d))
+}
+
+/// first `amount` cells visited by the curve, as flattened (x, y) pairs
+#[wasm_bindgen]
+pub fn all_curve_points(kind: CurveKind, n: u32, amount: u32) -> Vec<i32> {
+    all_points_in(kind.curve(), n, amount)
+        .flat_map(|(x, y)| [x as i32, y as i32])
+        .collect()
+}
+
+/// curve index of each (x, y) pair in `points`, an error for points outside the grid
+/// and for grids whose keys don't fit in 32 bits: n above 65536, or 3^10 for Peano
+#[wasm_bindgen]
+pub fn curve_keys(kind: CurveKind, n: u32, points: &[i32]) -> Result<Vec<u32>, JsError> {
+    Ok(keys_in(kind.curve(), n, points)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{key_side, keys_in, CurveKind, KeyError, SpaceFillingCurve};
+
+    const KINDS: [CurveKind; 5] = [
+        CurveKind::Hilbert,
+        CurveKind::ZOrder,
+        CurveKind::Peano,
+        CurveKind::Moore,
+        CurveKind::GrayCode,
+    ];
+
+    #[test]
+    fn curves_are_bijections() {
+        for kind in KINDS {
+            let curve = kind.curve();
+            for n in [1, 2, 3, 8, 9, 27, 32] {
+                let side = curve.side(n);
+                assert!(side >= n);
+                let mut seen = vec![false; (side * side) as usize];
+                for d in 0..side * side {
+                    let (x, y) = curve.index_to_point(n, d);
+                    assert!((0..side).contains(&x) && (0..side).contains(&y));
+                    assert!(!seen[(y * side + x) as usize], "{:?} visits twice", kind);
+                    seen[(y * side + x) as usize] = true;
+                    assert_eq!(curve.point_to_index(n, x, y), d, "{:?} n = {}", kind, n);
+                }
+            }
+        }
+    }
+
+    fn assert_unit_steps(curve: &dyn SpaceFillingCurve, n: i64) {
+        let side = curve.side(n);
+        for d in 1..side * side {
+            let (x0, y0) = curve.index_to_point(n, d - 1);
+            let (x1, y1) = curve.index_to_point(n, d);
+            assert_eq!((x0 - x1).abs() + (y0 - y1).abs(), 1, "n = {} d = {}", n, d);
+        }
+    }
+
+    #[test]
+    fn continuous_curves_take_unit_steps() {
+        for kind in [CurveKind::Hilbert, CurveKind::Peano, CurveKind::Moore] {
+            for n in [2, 3, 16, 27, 81] {
+                assert_unit_steps(kind.curve(), n);
+            }
+        }
+    }
+
+    #[test]
+    fn keys_fit_in_32_bits() {
+        let peano = CurveKind::Peano.curve();
+        let n = 3u32.pow(10);
+        let last = n as i32 - 1;
+        assert_eq!(
+            keys_in(peano, n, &[last, last]).unwrap(),
+            [peano.point_to_index(n as i64, last as i64, last as i64) as u32]
+        );
+        //3^22 cells, the last key is 31381059608
+        assert_eq!(
+            keys_in(peano, n * 3, &[0, 0]),
+            Err(KeyError::GridTooLarge(n * 3))
+        );
+        for kind in KINDS.into_iter().filter(|&kind| kind != CurveKind::Peano) {
+            assert!(key_side(kind.curve(), 1 << 16).is_ok(), "{:?}", kind);
+            assert_eq!(
+                key_side(kind.curve(), (1 << 16) + 1),
+                Err(KeyError::GridTooLarge((1 << 16) + 1))
+            );
+        }
+        assert_eq!(
+            keys_in(CurveKind::ZOrder.curve(), 4, &[1, 4]),
+            Err(KeyError::OutOfRange(0))
+        );
+    }
+
+    #[test]
+    fn moore_is_closed() {
+        let curve = CurveKind::Moore.curve();
+        for n in [2, 4, 64] {
+            let (x0, y0) = curve.index_to_point(n, 0);
+            let (x1, y1) = curve.index_to_point(n, n * n - 1);
+            assert_eq!((x0 - x1).abs() + (y0 - y1).abs(), 1);
+        }
+    }
+
+    #[test]
+    fn known_orders() {
+        let z: Vec<_> = (0..6)
+            .map(|d| CurveKind::ZOrder.curve().index_to_point(4, d))
+            .collect();
+        assert_eq!(z, [(0, 0), (1, 0), (0, 1), (1, 1), (2, 0), (3, 0)]);
+        let gray: Vec<_> = (0..4)
+            .map(|d| CurveKind::GrayCode.curve().index_to_point(2, d))
+            .collect();
+        assert_eq!(gray, [(0, 0), (1, 0), (1, 1), (0, 1)]);
+        let peano: Vec<_> = (0..9)
+            .map(|d| CurveKind::Peano.curve().index_to_point(3, d))
+            .collect();
+        assert_eq!(
+            peano,
+            [
+                (0, 0),
+                (0, 1),
+                (0, 2),
+                (1, 2),
+                (1, 1),
+                (1, 0),
+                (2, 0),
+                (2, 1),
+                (2, 2)
+            ]
+        );
+        assert_eq!(
+            super::all_curve_points(CurveKind::Hilbert, 2, 4),
+            [0, 0, 0, 1, 1, 1, 1, 0]
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use super::SpaceFillingCurve;
+use crate::hilbert::{d2xy, xy2d};
+
+/// Four Hilbert curves of half the side joined into a loop:
+/// up the left half, then down the right half, ending next to where it started
+pub struct Moore;
+
+impl SpaceFillingCurve for Moore {
+    fn side(&self, n: i64) -> i64 {
+        (n as u64).next_power_of_two().max(2) as i64
+    }
+
+    fn index_to_point(&self, n: i64, d: i64) -> (i64, i64) {
+        let s = self.side(n) / 2;
+        let quadrant = d / (s * s);
+        let (hx, hy) = d2xy(s, d % (s * s));
+        match quadrant {
+            0 => (s - 1 - hy, hx),
+            1 => (s - 1 - hy, s + hx),
+            2 => (s + hy, 2 * s - 1 - hx),
+            _ => (s + hy, s - 1 - hx),
+        }
+    }
+
+    fn point_to_index(&self, n: i64, x: i64, y: i64) -> i64 {
+        let s = self.side(n) / 2;
+        let (quadrant, hx, hy) = match (x >= s, y >= s) {
+            (false, false) => (0, y, s - 1 - x),
+            (false, true) => (1, y - s, s - 1 - x),
+            (true, true) => (2, 2 * s - 1 - y, x - s),
+            (true, false) => (3, s - 1 - y, x - s),
+        };
+        quadrant * s * s + xy2d(s, hx, hy)
+    }
+}
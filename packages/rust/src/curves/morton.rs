@@ -0,0 +1,48 @@
+use super::SpaceFillingCurve;
+
+/// Z-order, x takes the even bits of the index and y the odd ones
+pub struct Morton;
+
+//spreads the low 32 bits of v so there is a zero between each of them
+fn spread(v: i64) -> i64 {
+    let mut v = v as u64 & 0xffff_ffff;
+    v = (v | (v << 16)) & 0x0000_ffff_0000_ffff;
+    v = (v | (v << 8)) & 0x00ff_00ff_00ff_00ff;
+    v = (v | (v << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+    v = (v | (v << 2)) & 0x3333_3333_3333_3333;
+    v = (v | (v << 1)) & 0x5555_5555_5555_5555;
+    v as i64
+}
+
+//inverse of spread, keeps the even bits of v packed together
+fn compact(v: i64) -> i64 {
+    let mut v = v as u64 & 0x5555_5555_5555_5555;
+    v = (v | (v >> 1)) & 0x3333_3333_3333_3333;
+    v = (v | (v >> 2)) & 0x0f0f_0f0f_0f0f_0f0f;
+    v = (v | (v >> 4)) & 0x00ff_00ff_00ff_00ff;
+    v = (v | (v >> 8)) & 0x0000_ffff_0000_ffff;
+    v = (v | (v >> 16)) & 0x0000_0000_ffff_ffff;
+    v as i64
+}
+
+pub(super) fn interleave(x: i64, y: i64) -> i64 {
+    spread(x) | (spread(y) << 1)
+}
+
+pub(super) fn deinterleave(d: i64) -> (i64, i64) {
+    (compact(d), compact(d >> 1))
+}
+
+impl SpaceFillingCurve for Morton {
+    fn side(&self, n: i64) -> i64 {
+        (n as u64).next_power_of_two() as i64
+    }
+
+    fn index_to_point(&self, _: i64, d: i64) -> (i64, i64) {
+        deinterleave(d)
+    }
+
+    fn point_to_index(&self, _: i64, x: i64, y: i64) -> i64 {
+        interleave(x, y)
+    }
+}
@@ -0,0 +1,62 @@
+use super::SpaceFillingCurve;
+
+/// Peano's original curve on a 3^k x 3^k grid.
+/// The index is read as ternary digits a1 a2 a3 ..., alternating between x and y,
+/// and each digit is mirrored (t -> 2 - t) when the sum of the previous digits
+/// of the other coordinate is odd
+pub struct Peano;
+
+//number of ternary digits per coordinate
+fn levels(n: i64) -> u32 {
+    let mut side = 1;
+    let mut levels = 0;
+    while side < n {
+        side *= 3;
+        levels += 1;
+    }
+    levels
+}
+
+fn mirror(digit: i64, sum: i64) -> i64 {
+    if sum % 2 == 1 {
+        2 - digit
+    } else {
+        digit
+    }
+}
+
+impl SpaceFillingCurve for Peano {
+    fn side(&self, n: i64) -> i64 {
+        3i64.pow(levels(n))
+    }
+
+    fn index_to_point(&self, n: i64, d: i64) -> (i64, i64) {
+        let levels = levels(n);
+        let (mut x, mut y) = (0, 0);
+        let (mut x_sum, mut y_sum) = (0, 0);
+        for level in (0..levels).rev() {
+            let pair = d / 9i64.pow(level) % 9;
+            let (a_x, a_y) = (pair / 3, pair % 3);
+            x = x * 3 + mirror(a_x, y_sum);
+            x_sum += a_x;
+            y = y * 3 + mirror(a_y, x_sum);
+            y_sum += a_y;
+        }
+        (x, y)
+    }
+
+    fn point_to_index(&self, n: i64, x: i64, y: i64) -> i64 {
+        let levels = levels(n);
+        let mut d = 0;
+        let (mut x_sum, mut y_sum) = (0, 0);
+        for level in (0..levels).rev() {
+            let place = 3i64.pow(level);
+            let a_x = mirror(x / place % 3, y_sum);
+            x_sum += a_x;
+            let a_y = mirror(y / place % 3, x_sum);
+            y_sum += a_y;
+            d = d * 9 + a_x * 3 + a_y;
+        }
+        d
+    }
+}
@@ -0,0 +1,107 @@
+use crate::codec::{decode_symbol, decode_table, wasm_codec, Codec, DecodeError, INVALID};
+use wasm_bindgen::prelude::*;
+
+static HEX_LOWER: [u8; 16] = *b"0123456789abcdef";
+static HEX_UPPER: [u8; 16] = *b"0123456789ABCDEF";
+
+//both cases decode to the same value
+static HEX_DECODE: [u8; 256] = {
+    let mut table = decode_table(&HEX_LOWER);
+    let upper = decode_table(&HEX_UPPER);
+    let mut i = 0;
+    while i < 256 {
+        if upper[i] != INVALID {
+            table[i] = upper[i];
+        }
+        i += 1;
+    }
+    table
+};
+
+/// Base16, two characters per byte
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Hex {
+    pub uppercase: bool,
+}
+
+impl Codec for Hex {
+    fn encoded_len(&self, len: usize) -> usize {
+        len * 2
+    }
+
+    fn encode_into(&self, bytes: &[u8], out: &mut Vec<u8>) {
+        let table = if self.uppercase {
+            &HEX_UPPER
+        } else {
+            &HEX_LOWER
+        };
+        let start = out.len();
+        out.resize(start + bytes.len() * 2, 0);
+        bytes
+            .iter()
+            .zip(out[start..].chunks_exact_mut(2))
+            .for_each(|(byte, chars)| {
+                chars[0] = table[(byte >> 4) as usize];
+                chars[1] = table[(byte & 0xf) as usize];
+            });
+    }
+
+    fn decode_into(&self, text: &[u8], out: &mut Vec<u8>) -> Result<(), DecodeError> {
+        if !text.len().is_multiple_of(2) {
+            return Err(DecodeError::InvalidLength(text.len()));
+        }
+        out.reserve(text.len() / 2);
+        for (i, pair) in text.chunks_exact(2).enumerate() {
+            let high = decode_symbol(&HEX_DECODE, pair[0], i * 2)?;
+            let low = decode_symbol(&HEX_DECODE, pair[1], i * 2 + 1)?;
+            out.push(high << 4 | low);
+        }
+        Ok(())
+    }
+}
+
+wasm_codec!(HexDecoder, Hex, to_hex, from_hex);
+
+#[wasm_bindgen]
+impl HexDecoder {
+    pub fn new(uppercase: bool) -> Self {
+        Self::with_codec(Hex { uppercase })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Hex, HexDecoder};
+    use crate::codec::{assert_round_trip, DecodeError};
+
+    #[test]
+    fn hex_basic() {
+        let mut hex = HexDecoder::new(false);
+        hex.to_hex(&[0x00, 0x7f, 0xab, 0xff]);
+        assert_eq!(hex.output(), b"007fabff");
+        let mut hex = HexDecoder::new(true);
+        hex.to_hex(&[0x00, 0x7f, 0xab, 0xff]);
+        assert_eq!(hex.output(), b"007FABFF");
+        assert_round_trip(&Hex { uppercase: false });
+        assert_round_trip(&Hex { uppercase: true });
+    }
+
+    #[test]
+    fn hex_decode() {
+        let mut out = vec![];
+        let decode = |text: &[u8], out: &mut Vec<u8>| {
+            out.clear();
+            crate::codec::Codec::decode_into(&Hex::default(), text, out)
+        };
+        decode(b"7fAbfF", &mut out).unwrap();
+        assert_eq!(out, [0x7f, 0xab, 0xff]);
+        assert_eq!(decode(b"abc", &mut out), Err(DecodeError::InvalidLength(3)));
+        assert_eq!(
+            decode(b"0g", &mut out),
+            Err(DecodeError::InvalidSymbol {
+                index: 1,
+                byte: b'g'
+            })
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use super::d2xy;
+use wasm_bindgen::prelude::*;
+
+//bytes around each one taken into account by the entropy scheme
+const ENTROPY_WINDOW: usize = 32;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteScheme {
+    /// black for 0x00, white for 0xff, blue for printable ASCII, green for control, red for the rest
+    ByteClass,
+    /// local Shannon entropy, dark for repetitive data, magenta for compressed or encrypted
+    Entropy,
+}
+
+fn class_color(byte: u8) -> [u8; 3] {
+    match byte {
+        0x00 => [0, 0, 0],
+        0xff => [255, 255, 255],
+        0x20..=0x7e => [55, 126, 184],
+        0x01..=0x1f | 0x7f => [77, 175, 74],
+        _ => [228, 26, 28],
+    }
+}
+
+//entropy of the window around `index`, 0 to 1
+fn window_entropy(bytes: &[u8], index: usize) -> f64 {
+    let size = ENTROPY_WINDOW.min(bytes.len());
+    let start = index
+        .saturating_sub(ENTROPY_WINDOW / 2)
+        .min(bytes.len() - size);
+    let mut counts = [0u32; 256];
+    bytes[start..start + size]
+        .iter()
+        .for_each(|&b| counts[b as usize] += 1);
+    if size < 2 {
+        return 0.0;
+    }
+    let entropy: f64 = counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / size as f64;
+            -p * p.log2()
+        })
+        .sum();
+    //the most a window this size can reach is every byte being different
+    entropy / (size as f64).log2()
+}
+
+fn entropy_color(entropy: f64) -> [u8; 3] {
+    let curve = |x: f64| (4.0 * x - 4.0 * x * x).powi(4);
+    let r = if entropy > 0.5 {
+        curve(entropy - 0.5)
+    } else {
+        0.0
+    };
+    let b = entropy * entropy;
+    [(r * 255.0).round() as u8, 0, (b * 255.0).round() as u8]
+}
+
+/// side of the square image `len` bytes are laid out in, a power of two
+#[wasm_bindgen]
+pub fn byte_image_side(len: usize) -> usize {
+    let mut side = 1;
+    while side * side < len {
+        side *= 2;
+    }
+    side
+}
+
+/// RGBA image of `bytes` laid along the Hilbert curve, `byte_image_side` pixels wide,
+/// the cells past the end of the data are left white like the background of `calc_set`
+#[wasm_bindgen]
+pub fn byte_image(bytes: &[u8], scheme: ByteScheme) -> Vec<u8> {
+    let side = byte_image_side(bytes.len());
+    let mut image = vec![255u8; side * side * 4];
+    for (d, &byte) in bytes.iter().enumerate() {
+        let color = match scheme {
+            ByteScheme::ByteClass => class_color(byte),
+            ByteScheme::Entropy => entropy_color(window_entropy(bytes, d)),
+        };
+        let (x, y) = d2xy(side as i64, d as i64);
+        let index_now = (y as usize * side + x as usize) * 4;
+        image[index_now..index_now + 3].copy_from_slice(&color);
+    }
+    image
+}
+
+#[cfg(test)]
+mod test {
+    use super::{byte_image, byte_image_side, window_entropy, ByteScheme};
+
+    #[test]
+    fn byte_classes() {
+        assert_eq!(byte_image_side(0), 1);
+        assert_eq!(byte_image_side(16), 4);
+        assert_eq!(byte_image_side(17), 8);
+
+        let image = byte_image(&[0x00, b'a', 0x0a, 0xff, 0x80], ByteScheme::ByteClass);
+        assert_eq!(image.len(), 4 * 4 * 4);
+        let pixel = |x: usize, y: usize| &image[(y * 4 + x) * 4..(y * 4 + x) * 4 + 4];
+        //the first cells of the curve are (0, 0), (1, 0), (1, 1), (0, 1), (0, 2)
+        assert_eq!(pixel(0, 0), [0, 0, 0, 255]);
+        assert_eq!(pixel(1, 0), [55, 126, 184, 255]);
+        assert_eq!(pixel(1, 1), [77, 175, 74, 255]);
+        assert_eq!(pixel(0, 1), [255, 255, 255, 255]);
+        assert_eq!(pixel(0, 2), [228, 26, 28, 255]);
+    }
+
+    #[test]
+    fn entropy() {
+        let zeros = [0u8; 100];
+        assert_eq!(window_entropy(&zeros, 50), 0.0);
+        let counting: Vec<u8> = (0..=255).collect();
+        assert!((window_entropy(&counting, 0) - 1.0).abs() < 1e-12);
+        assert!((window_entropy(&counting, 255) - 1.0).abs() < 1e-12);
+
+        let image = byte_image(&zeros, ByteScheme::Entropy);
+        assert_eq!(&image[..4], [0, 0, 0, 255]);
+        let image = byte_image(&counting, ByteScheme::Entropy);
+        assert_eq!(&image[..4], [255, 0, 255, 255]);
+        assert_eq!(byte_image(&[], ByteScheme::Entropy), [255; 4]);
+    }
+}
@@ -0,0 +1,124 @@
+use wasm_bindgen::prelude::*;
+
+/// Generalized Hilbert ("gilbert") curve through a width x height rectangle,
+/// after Jakub Červený's gilbert2d. It splits the longer side in two or three
+/// parts like the Hilbert curve does, so any size is covered with unit steps,
+/// except for a single diagonal step when the long side is odd and the short one even,
+/// as no unit step walk joins the two ends of the long side then
+pub fn gilbert_in(width: u32, height: u32) -> impl Iterator<Item = (i64, i64)> {
+    let (w, h) = (width as i64, height as i64);
+    let mut cells = Vec::with_capacity((w * h) as usize);
+    let mut visit = |x, y| cells.push((x, y));
+    if w == 0 || h == 0 {
+        return cells.into_iter();
+    }
+    if w >= h {
+        generate(&mut visit, (0, 0), (w, 0), (0, h));
+    } else {
+        generate(&mut visit, (0, 0), (0, h), (w, 0));
+    }
+    cells.into_iter()
+}
+
+/// every cell of a width x height rectangle in curve order, as flattened (x, y) pairs
+#[wasm_bindgen]
+pub fn all_gilbert(width: u32, height: u32) -> Vec<i32> {
+    gilbert_in(width, height)
+        .flat_map(|(x, y)| [x as i32, y as i32])
+        .collect()
+}
+
+//walks the rectangle at `origin` spanned by the major axis `a` and minor axis `b`
+fn generate(visit: &mut impl FnMut(i64, i64), origin: (i64, i64), a: (i64, i64), b: (i64, i64)) {
+    let (x, y) = origin;
+    let (ax, ay) = a;
+    let (bx, by) = b;
+    let w = (ax + ay).abs();
+    let h = (bx + by).abs();
+    let (dax, day) = (ax.signum(), ay.signum());
+    let (dbx, dby) = (bx.signum(), by.signum());
+
+    if h == 1 {
+        (0..w).for_each(|i| visit(x + i * dax, y + i * day));
+        return;
+    }
+    if w == 1 {
+        (0..h).for_each(|i| visit(x + i * dbx, y + i * dby));
+        return;
+    }
+
+    let (mut ax2, mut ay2) = (ax.div_euclid(2), ay.div_euclid(2));
+    let (mut bx2, mut by2) = (bx.div_euclid(2), by.div_euclid(2));
+    let w2 = (ax2 + ay2).abs();
+    let h2 = (bx2 + by2).abs();
+
+    if 2 * w > 3 * h {
+        //long and thin, split along the major axis only
+        if w2 % 2 == 1 && w > 2 {
+            ax2 += dax;
+            ay2 += day;
+        }
+        generate(visit, (x, y), (ax2, ay2), b);
+        generate(visit, (x + ax2, y + ay2), (ax - ax2, ay - ay2), b);
+    } else {
+        //up along b, across the far half, then back down
+        if h2 % 2 == 1 && h > 2 {
+            bx2 += dbx;
+            by2 += dby;
+        }
+        generate(visit, (x, y), (bx2, by2), (ax2, ay2));
+        generate(visit, (x + bx2, y + by2), a, (bx - bx2, by - by2));
+        generate(
+            visit,
+            (x + (ax - dax) + (bx2 - dbx), y + (ay - day) + (by2 - dby)),
+            (-bx2, -by2),
+            (-(ax - ax2), -(ay - ay2)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{all_gilbert, gilbert_in};
+    use crate::hilbert::d2xy;
+
+    #[test]
+    fn covers_rectangles() {
+        for width in 0..40 {
+            for height in 0..40 {
+                let cells: Vec<_> = gilbert_in(width, height).collect();
+                assert_eq!(cells.len() as u32, width * height);
+                let mut seen = vec![false; cells.len()];
+                for &(x, y) in &cells {
+                    assert!(x >= 0 && x < width as i64 && y >= 0 && y < height as i64);
+                    let cell = (y * width as i64 + x) as usize;
+                    assert!(
+                        !seen[cell],
+                        "{}x{} visits ({}, {}) twice",
+                        width, height, x, y
+                    );
+                    seen[cell] = true;
+                }
+                let (long, short) = (width.max(height), width.min(height));
+                let diagonals = cells
+                    .windows(2)
+                    .filter(|p| (p[0].0 - p[1].0).abs() + (p[0].1 - p[1].1).abs() != 1)
+                    .count();
+                let allowed = (long % 2 == 1 && short % 2 == 0) as usize;
+                assert!(diagonals <= allowed, "{}x{}", width, height);
+            }
+        }
+    }
+
+    #[test]
+    fn squares_match_hilbert_shape() {
+        let cells: Vec<_> = gilbert_in(8, 8).collect();
+        assert_eq!(cells[0], (0, 0));
+        assert_eq!(cells[63], (7, 0));
+        //same cells as the power of two curve, possibly mirrored
+        let hilbert: Vec<_> = (0..64).map(|d| d2xy(8, d)).collect();
+        let mirrored: Vec<_> = hilbert.iter().map(|&(x, y)| (y, x)).collect();
+        assert!(cells == hilbert || cells == mirrored);
+        assert_eq!(all_gilbert(3, 2), [0, 0, 0, 1, 1, 1, 2, 1, 2, 0, 1, 0]);
+    }
+}
@@ -0,0 +1,322 @@
+use super::xy2d;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use wasm_bindgen::prelude::*;
+
+//resolution of the grid the box centers are snapped to before taking their key
+const KEY_SIDE: i64 = 1 << 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BBox {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl BBox {
+    pub const EMPTY: BBox = BBox {
+        min_x: f64::INFINITY,
+        min_y: f64::INFINITY,
+        max_x: f64::NEG_INFINITY,
+        max_y: f64::NEG_INFINITY,
+    };
+
+    pub fn new(min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> Self {
+        Self {
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        }
+    }
+
+    pub fn point(x: f64, y: f64) -> Self {
+        Self::new(x, y, x, y)
+    }
+
+    pub fn extend(&mut self, other: &BBox) {
+        self.min_x = self.min_x.min(other.min_x);
+        self.min_y = self.min_y.min(other.min_y);
+        self.max_x = self.max_x.max(other.max_x);
+        self.max_y = self.max_y.max(other.max_y);
+    }
+
+    pub fn intersects(&self, other: &BBox) -> bool {
+        self.min_x <= other.max_x
+            && other.min_x <= self.max_x
+            && self.min_y <= other.max_y
+            && other.min_y <= self.max_y
+    }
+
+    /// squared distance from (x, y) to the closest point of the box, 0 inside it
+    pub fn distance2(&self, x: f64, y: f64) -> f64 {
+        let dx = (self.min_x - x).max(0.0).max(x - self.max_x);
+        let dy = (self.min_y - y).max(0.0).max(y - self.max_y);
+        dx * dx + dy * dy
+    }
+}
+
+/// Static R-tree packed along the Hilbert curve: items are sorted by the key of
+/// their center and grouped `node_size` at a time, each group becoming a parent box,
+/// until a single root is left. All levels live in flat arrays, leaves first
+#[wasm_bindgen]
+pub struct HilbertIndex {
+    node_size: usize,
+    //boxes of every node, level by level
+    boxes: Vec<BBox>,
+    //item id for the leaves, position of the first child for the other nodes
+    indices: Vec<u32>,
+    //end of each level in `boxes`
+    level_bounds: Vec<usize>,
+}
+
+impl HilbertIndex {
+    pub fn new(items: &[BBox], node_size: usize) -> Self {
+        let node_size = node_size.max(2);
+        let mut bounds = BBox::EMPTY;
+        items.iter().for_each(|b| bounds.extend(b));
+        let width = bounds.max_x - bounds.min_x;
+        let height = bounds.max_y - bounds.min_y;
+        let scale = |value: f64, min: f64, size: f64| {
+            if size > 0.0 {
+                ((value - min) / size * (KEY_SIDE - 1) as f64) as i64
+            } else {
+                0
+            }
+        };
+        let keys: Vec<i64> = items
+            .iter()
+            .map(|b| {
+                let x = scale((b.min_x + b.max_x) / 2.0, bounds.min_x, width);
+                let y = scale((b.min_y + b.max_y) / 2.0, bounds.min_y, height);
+                xy2d(KEY_SIDE, x, y)
+            })
+            .collect();
+        let mut order: Vec<u32> = (0..items.len() as u32).collect();
+        order.sort_by_key(|&i| keys[i as usize]);
+
+        let mut boxes: Vec<BBox> = order.iter().map(|&i| items[i as usize]).collect();
+        let mut indices = order;
+        let mut level_bounds = vec![boxes.len()];
+        let mut start = 0;
+        while boxes.len() - start > 1 {
+            let end = boxes.len();
+            for first in (start..end).step_by(node_size) {
+                let mut parent = BBox::EMPTY;
+                boxes[first..(first + node_size).min(end)]
+                    .iter()
+                    .for_each(|b| parent.extend(b));
+                boxes.push(parent);
+                indices.push(first as u32);
+            }
+            start = end;
+            level_bounds.push(boxes.len());
+        }
+        Self {
+            node_size,
+            boxes,
+            indices,
+            level_bounds,
+        }
+    }
+
+    //positions of the children of the node at `pos` on `level`
+    fn children(&self, pos: usize, level: usize) -> std::ops::Range<usize> {
+        let first = self.indices[pos] as usize;
+        first..(first + self.node_size).min(self.level_bounds[level - 1])
+    }
+
+    /// ids of the items whose box intersects `query`, in no particular order
+    pub fn search_box(&self, query: &BBox) -> Vec<u32> {
+        let mut found = vec![];
+        if self.boxes.is_empty() {
+            return found;
+        }
+        let mut stack = vec![(self.boxes.len() - 1, self.level_bounds.len() - 1)];
+        while let Some((pos, level)) = stack.pop() {
+            if !self.boxes[pos].intersects(query) {
+                continue;
+            }
+            if level == 0 {
+                found.push(self.indices[pos]);
+            } else {
+                stack.extend(self.children(pos, level).map(|child| (child, level - 1)));
+            }
+        }
+        found
+    }
+
+    /// ids of the `k` items closest to (x, y), nearest first
+    pub fn nearest(&self, x: f64, y: f64, k: usize) -> Vec<u32> {
+        let mut found = vec![];
+        if self.boxes.is_empty() || k == 0 {
+            return found;
+        }
+        let mut queue = BinaryHeap::new();
+        queue.push(Candidate {
+            distance2: 0.0,
+            pos: self.boxes.len() - 1,
+            level: self.level_bounds.len() - 1,
+        });
+        //leaves come out of the queue only once nothing unexplored can be closer
+        while let Some(Candidate { pos, level, .. }) = queue.pop() {
+            if level == 0 {
+                found.push(self.indices[pos]);
+                if found.len() == k {
+                    break;
+                }
+                continue;
+            }
+            for child in self.children(pos, level) {
+                queue.push(Candidate {
+                    distance2: self.boxes[child].distance2(x, y),
+                    pos: child,
+                    level: level - 1,
+                });
+            }
+        }
+        found
+    }
+}
+
+#[wasm_bindgen]
+impl HilbertIndex {
+    /// index over boxes given as flattened (min_x, min_y, max_x, max_y)
+    pub fn from_boxes(boxes: &[f64], node_size: usize) -> HilbertIndex {
+        let items: Vec<BBox> = boxes
+            .chunks_exact(4)
+            .map(|b| BBox::new(b[0], b[1], b[2], b[3]))
+            .collect();
+        Self::new(&items, node_size)
+    }
+
+    /// index over points given as flattened (x, y)
+    pub fn from_points(points: &[f64], node_size: usize) -> HilbertIndex {
+        let items: Vec<BBox> = points
+            .chunks_exact(2)
+            .map(|p| BBox::point(p[0], p[1]))
+            .collect();
+        Self::new(&items, node_size)
+    }
+
+    pub fn len(&self) -> usize {
+        self.level_bounds[0]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// ids of the items intersecting the rectangle
+    pub fn search(&self, min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> Vec<u32> {
+        self.search_box(&BBox::new(min_x, min_y, max_x, max_y))
+    }
+
+    /// ids of the `k` items closest to (x, y), nearest first
+    pub fn k_nearest(&self, x: f64, y: f64, k: usize) -> Vec<u32> {
+        self.nearest(x, y, k)
+    }
+}
+
+//min heap entry ordered by distance
+struct Candidate {
+    distance2: f64,
+    pos: usize,
+    level: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        //reversed so the heap pops the closest first, leaves before nodes on ties
+        other
+            .distance2
+            .total_cmp(&self.distance2)
+            .then(other.level.cmp(&self.level))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BBox, HilbertIndex};
+    use crate::rng::SeededRng;
+
+    fn random_boxes(rng: &mut SeededRng, count: usize) -> Vec<BBox> {
+        let mut random = || rng.next_f64();
+        (0..count)
+            .map(|_| {
+                let (x, y) = (random() * 1000.0, random() * 1000.0);
+                BBox::new(x, y, x + random() * 20.0, y + random() * 20.0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn search_matches_linear_scan() {
+        let mut rng = SeededRng::new(11);
+        let items = random_boxes(&mut rng, 2000);
+        let mut random = || rng.next_f64();
+        let index = HilbertIndex::new(&items, 16);
+        assert_eq!(index.len(), 2000);
+        for _ in 0..50 {
+            let (x, y) = (random() * 1000.0, random() * 1000.0);
+            let query = BBox::new(x, y, x + random() * 200.0, y + random() * 200.0);
+            let mut found = index.search_box(&query);
+            found.sort();
+            let expected: Vec<u32> = (0..items.len() as u32)
+                .filter(|&i| items[i as usize].intersects(&query))
+                .collect();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn nearest_matches_linear_scan() {
+        let mut rng = SeededRng::new(12);
+        let mut random = || rng.next_f64();
+        let points: Vec<f64> = (0..3000).map(|_| random() * 500.0).collect();
+        let index = HilbertIndex::from_points(&points, 8);
+        for _ in 0..50 {
+            let (x, y) = (random() * 500.0, random() * 500.0);
+            let distance = |i: u32| {
+                let (px, py) = (points[i as usize * 2], points[i as usize * 2 + 1]);
+                (px - x).powi(2) + (py - y).powi(2)
+            };
+            let found = index.k_nearest(x, y, 10);
+            let mut expected: Vec<u32> = (0..1500).collect();
+            expected.sort_by(|&a, &b| distance(a).total_cmp(&distance(b)));
+            let found: Vec<f64> = found.into_iter().map(distance).collect();
+            let expected: Vec<f64> = expected[..10].iter().map(|&i| distance(i)).collect();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn small_indices() {
+        let empty = HilbertIndex::from_points(&[], 4);
+        assert!(empty.is_empty());
+        assert!(empty.search(0.0, 0.0, 1.0, 1.0).is_empty());
+        assert!(empty.k_nearest(0.0, 0.0, 3).is_empty());
+
+        let single = HilbertIndex::from_points(&[1.0, 1.0], 4);
+        assert_eq!(single.search(0.0, 0.0, 1.0, 1.0), [0]);
+        assert_eq!(single.k_nearest(5.0, 5.0, 3), [0]);
+
+        let line = HilbertIndex::from_points(&[0.0, 0.0, 3.0, 0.0, 1.0, 0.0, 2.0, 0.0], 2);
+        assert_eq!(line.k_nearest(2.9, 0.0, 4), [1, 3, 2, 0]);
+    }
+}
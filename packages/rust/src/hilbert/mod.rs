@@ -0,0 +1,183 @@
+mod binvis;
+mod gilbert;
+mod index;
+mod nd;
+
+pub use binvis::{byte_image, byte_image_side, ByteScheme};
+pub use gilbert::{all_gilbert, gilbert_in};
+pub use index::{BBox, HilbertIndex};
+pub use nd::{
+    all_hilbert3, all_hilbert3_in, all_hilbert_nd, all_hilbert_nd_in, d2xyz, hilbert3_keys,
+    hilbert3_order, hilbert3_points, hilbert_nd_keys, xyz2d, CurveSizeError, HilbertNd,
+};
+
+use crate::curves::{all_points_in, keys_in, points_in, CurveKind, SpaceFillingCurve};
+use wasm_bindgen::prelude::*;
+
+pub struct Hilbert;
+
+impl SpaceFillingCurve for Hilbert {
+    fn side(&self, n: i64) -> i64 {
+        (n as u64).next_power_of_two() as i64
+    }
+
+    fn index_to_point(&self, n: i64, d: i64) -> (i64, i64) {
+        d2xy(n, d)
+    }
+
+    fn point_to_index(&self, n: i64, x: i64, y: i64) -> i64 {
+        xy2d(n, x, y)
+    }
+}
+
+pub(crate) fn d2xy(n: i64, d: i64) -> (i64, i64) {
+    let mut x: i64;
+    let mut y: i64;
+    let mut rx: i64;
+    let mut ry: i64;
+    let mut s: i64 = 1;
+    let mut t = d;
+    x = 0;
+    y = 0;
+
+    while s < n {
+        rx = 1 & (t / 2);
+        ry = 1 & (t ^ rx);
+        rot(s, &mut x, &mut y, rx, ry);
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2
+    }
+    (x, y)
+}
+
+pub(crate) fn xy2d(n: i64, x: i64, y: i64) -> i64 {
+    let n = (n as u64).next_power_of_two() as i64;
+    let mut x = x;
+    let mut y = y;
+    let mut rx: i64;
+    let mut ry: i64;
+    let mut s: i64 = n / 2;
+    let mut d: i64 = 0;
+
+    while s > 0 {
+        rx = ((x & s) > 0) as i64;
+        ry = ((y & s) > 0) as i64;
+        d += s * s * ((3 * rx) ^ ry);
+        rot(n, &mut x, &mut y, rx, ry);
+        s /= 2;
+    }
+    d
+}
+
+//rotate/flip a quadrant appropriately
+fn rot(n: i64, x: &mut i64, y: &mut i64, rx: i64, ry: i64) {
+    if ry == 0 {
+        if rx == 1 {
+            *x = n - 1 - *x;
+            *y = n - 1 - *y;
+        }
+
+        //Swap x and y
+        std::mem::swap(x, y);
+    }
+}
+
+pub fn all_hilbert_in(n: u32, amount: u32) -> impl Iterator<Item = (i64, i64)> {
+    all_points_in(&Hilbert, n, amount)
+}
+
+#[wasm_bindgen]
+pub fn all_hilbert(n: u32, amount: u32) -> Vec<i32> {
+    crate::curves::all_curve_points(CurveKind::Hilbert, n, amount)
+}
+
+/// Hilbert index of each (x, y) pair in `points`, an error for points outside the grid
+/// and for n above 65536, whose keys wouldn't fit in 32 bits
+#[wasm_bindgen]
+pub fn hilbert_keys(n: u32, points: &[i32]) -> Result<Vec<u32>, JsError> {
+    Ok(keys_in(&Hilbert, n, points)?)
+}
+
+/// (x, y) pairs of each Hilbert index in `keys`, flattened
+#[wasm_bindgen]
+pub fn hilbert_points(n: u32, keys: &[u32]) -> Result<Vec<i32>, JsError> {
+    Ok(points_in(&Hilbert, n, keys)?)
+}
+
+/// indices of the (x, y) pairs in `points` sorted along the Hilbert curve
+#[wasm_bindgen]
+pub fn hilbert_order(n: u32, points: &[i32]) -> Result<Vec<u32>, JsError> {
+    let keys = hilbert_keys(n, points)?;
+    let mut order: Vec<u32> = (0..keys.len() as u32).collect();
+    order.sort_by_key(|&i| keys[i as usize]);
+    Ok(order)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{d2xy, hilbert_keys, hilbert_order, hilbert_points, xy2d, Hilbert};
+    use crate::curves::{keys_in, points_in, KeyError};
+
+    #[test]
+    fn round_trip() {
+        for k in 0..=16 {
+            let n: i64 = 1 << k;
+            let cells = n * n;
+            let step = (cells / (1 << 16)).max(1);
+            for d in (0..cells).step_by(step as usize).chain([cells - 1]) {
+                let (x, y) = d2xy(n, d);
+                assert!(x < n && y < n);
+                assert_eq!(xy2d(n, x, y), d, "n = {} d = {}", n, d);
+            }
+        }
+    }
+
+    #[test]
+    fn consecutive_cells_are_neighbours() {
+        let n = 64;
+        for d in 1..n * n {
+            let (x0, y0) = d2xy(n, d - 1);
+            let (x1, y1) = d2xy(n, d);
+            assert_eq!((x0 - x1).abs() + (y0 - y1).abs(), 1);
+        }
+    }
+
+    #[test]
+    fn batch_conversions() {
+        let n = 1 << 16;
+        let points = [0, 0, 65535, 0, 123, 4567, 40000, 30000];
+        let keys = hilbert_keys(n, &points).unwrap();
+        assert_eq!(keys[0], 0);
+        assert_eq!(keys[1], u32::MAX);
+        assert_eq!(hilbert_points(n, &keys).unwrap(), points);
+
+        let order = hilbert_order(4, &[3, 0, 0, 0, 1, 1, 0, 3]).unwrap();
+        assert_eq!(order, [1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn keys_out_of_range() {
+        //2^17 x 2^17 cells don't have 32 bit keys
+        assert_eq!(
+            keys_in(&Hilbert, 1 << 17, &[0, 131071]),
+            Err(KeyError::GridTooLarge(1 << 17))
+        );
+        assert_eq!(
+            points_in(&Hilbert, 65537, &[0]),
+            Err(KeyError::GridTooLarge(65537))
+        );
+        //n = 5 fills an 8 x 8 grid
+        assert!(keys_in(&Hilbert, 5, &[7, 7]).is_ok());
+        assert_eq!(
+            keys_in(&Hilbert, 5, &[0, 0, 8, 0]),
+            Err(KeyError::OutOfRange(1))
+        );
+        assert_eq!(keys_in(&Hilbert, 5, &[0, -1]), Err(KeyError::OutOfRange(0)));
+        assert_eq!(
+            points_in(&Hilbert, 5, &[63, 64]),
+            Err(KeyError::OutOfRange(1))
+        );
+    }
+}
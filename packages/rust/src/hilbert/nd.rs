@@ -0,0 +1,343 @@
+use std::fmt;
+use wasm_bindgen::prelude::*;
+
+/// Hilbert curve through a cube of side 2^bits in any number of dimensions,
+/// using Skilling's transform ("Programming the Hilbert curve", 2004).
+/// Coordinates are `bits` wide and the index has `dims * bits` bits, so it must fit in 64
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HilbertNd {
+    dims: usize,
+    bits: u32,
+}
+
+/// A curve with no dimension, coordinates wider than 32 bits
+/// or an index wider than `index_bits`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CurveSizeError {
+    pub dims: usize,
+    pub bits: u32,
+    pub index_bits: u32,
+}
+
+impl fmt::Display for CurveSizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.dims {
+            0 => write!(f, "a curve needs at least one dimension"),
+            _ => write!(
+                f,
+                "{} dimensions of {} bits don't fit in 32 bit coordinates and a {} bit index",
+                self.dims, self.bits, self.index_bits
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CurveSizeError {}
+
+impl HilbertNd {
+    /// panics when `try_new` fails
+    pub fn new(dims: usize, bits: u32) -> Self {
+        Self::try_new(dims, bits).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    pub fn try_new(dims: usize, bits: u32) -> Result<Self, CurveSizeError> {
+        match dims > 0 && bits <= 32 && dims as u64 * bits as u64 <= 64 {
+            true => Ok(Self { dims, bits }),
+            false => Err(CurveSizeError {
+                dims,
+                bits,
+                index_bits: 64,
+            }),
+        }
+    }
+
+    /// the smallest curve covering a cube of side `n`, panics when `try_covering` fails
+    pub fn covering(dims: usize, n: u32) -> Self {
+        Self::new(dims, Self::bits_covering(n))
+    }
+
+    pub fn try_covering(dims: usize, n: u32) -> Result<Self, CurveSizeError> {
+        Self::try_new(dims, Self::bits_covering(n))
+    }
+
+    fn bits_covering(n: u32) -> u32 {
+        match n.max(1).checked_next_power_of_two() {
+            Some(side) => side.trailing_zeros(),
+            None => 32,
+        }
+    }
+
+    pub fn dims(&self) -> usize {
+        self.dims
+    }
+
+    pub fn side(&self) -> u64 {
+        1 << self.bits
+    }
+
+    /// writes the coordinates of the cell at index `d` into `point`
+    pub fn index_to_point(&self, d: u64, point: &mut [u32]) {
+        assert_eq!(point.len(), self.dims);
+        //the index bits are dealt round robin to the coordinates, most significant first
+        point.fill(0);
+        for bit in 0..self.bits * self.dims as u32 {
+            let axis = bit as usize % self.dims;
+            let from = self.bits * self.dims as u32 - 1 - bit;
+            point[axis] = point[axis] << 1 | (d >> from & 1) as u32;
+        }
+        self.transpose_to_axes(point);
+    }
+
+    /// index of the cell at `point`, coordinates must be inside the cube
+    pub fn point_to_index(&self, point: &[u32]) -> u64 {
+        assert_eq!(point.len(), self.dims);
+        let mut x = point.to_vec();
+        self.axes_to_transpose(&mut x);
+        let mut d = 0u64;
+        for bit in (0..self.bits).rev() {
+            for &axis in &x {
+                d = d << 1 | (axis >> bit & 1) as u64;
+            }
+        }
+        d
+    }
+
+    fn transpose_to_axes(&self, x: &mut [u32]) {
+        if self.bits == 0 {
+            return;
+        }
+        let n = self.dims;
+        //gray decode
+        let t = x[n - 1] >> 1;
+        for i in (1..n).rev() {
+            x[i] ^= x[i - 1];
+        }
+        x[0] ^= t;
+        //undo the rotations and reflections of each level
+        for level in 1..self.bits {
+            let q = 1 << level;
+            for i in (0..n).rev() {
+                exchange(x, i, q, q - 1);
+            }
+        }
+    }
+
+    fn axes_to_transpose(&self, x: &mut [u32]) {
+        if self.bits == 0 {
+            return;
+        }
+        let n = self.dims;
+        for level in (1..self.bits).rev() {
+            let q = 1 << level;
+            for i in 0..n {
+                exchange(x, i, q, q - 1);
+            }
+        }
+        //gray encode
+        for i in 1..n {
+            x[i] ^= x[i - 1];
+        }
+        let t = (1..self.bits)
+            .map(|level| 1 << level)
+            .filter(|q| x[n - 1] & q != 0)
+            .fold(0, |t, q| t ^ (q - 1));
+        x.iter_mut().for_each(|axis| *axis ^= t);
+    }
+}
+
+//inverts the low bits of x[0] or exchanges them with x[i]
+fn exchange(x: &mut [u32], i: usize, q: u32, p: u32) {
+    if x[i] & q != 0 {
+        x[0] ^= p;
+    } else {
+        let t = (x[0] ^ x[i]) & p;
+        x[0] ^= t;
+        x[i] ^= t;
+    }
+}
+
+pub fn d2xyz(n: i64, d: i64) -> (i64, i64, i64) {
+    let mut point = [0; 3];
+    HilbertNd::covering(3, n as u32).index_to_point(d as u64, &mut point);
+    (point[0] as i64, point[1] as i64, point[2] as i64)
+}
+
+pub fn xyz2d(n: i64, x: i64, y: i64, z: i64) -> i64 {
+    HilbertNd::covering(3, n as u32).point_to_index(&[x as u32, y as u32, z as u32]) as i64
+}
+
+pub fn all_hilbert3_in(n: u32, amount: u32) -> impl Iterator<Item = (i64, i64, i64)> {
+    (0..amount as i64).map(move |d| d2xyz(n as i64, d))
+}
+
+/// first `amount` cells of the 3D curve through an n x n x n cube, as flattened (x, y, z)
+#[wasm_bindgen]
+pub fn all_hilbert3(n: u32, amount: u32) -> Result<Vec<i32>, JsError> {
+    HilbertNd::try_covering(3, n)?;
+    Ok(all_hilbert3_in(n, amount)
+        .flat_map(|(x, y, z)| [x as i32, y as i32, z as i32])
+        .collect())
+}
+
+//curve of the 3D exports, whose keys are 32 bit: n is at most 1024
+fn key_curve(n: u32) -> Result<HilbertNd, CurveSizeError> {
+    let curve = HilbertNd::try_covering(3, n)?;
+    match curve.bits * 3 <= 32 {
+        true => Ok(curve),
+        false => Err(CurveSizeError {
+            dims: 3,
+            bits: curve.bits,
+            index_bits: 32,
+        }),
+    }
+}
+
+/// Hilbert index of each (x, y, z) triple in `points`,
+/// an error when n is above 1024 as the keys wouldn't fit in 32 bits
+#[wasm_bindgen]
+pub fn hilbert3_keys(n: u32, points: &[i32]) -> Result<Vec<u32>, JsError> {
+    let curve = key_curve(n)?;
+    Ok(points
+        .chunks_exact(3)
+        .map(|p| curve.point_to_index(&[p[0] as u32, p[1] as u32, p[2] as u32]) as u32)
+        .collect())
+}
+
+/// (x, y, z) triples of each Hilbert index in `keys`, flattened
+#[wasm_bindgen]
+pub fn hilbert3_points(n: u32, keys: &[u32]) -> Result<Vec<i32>, JsError> {
+    let curve = HilbertNd::try_covering(3, n)?;
+    let mut point = [0; 3];
+    Ok(keys
+        .iter()
+        .flat_map(|&d| {
+            curve.index_to_point(d as u64, &mut point);
+            point.map(|c| c as i32)
+        })
+        .collect())
+}
+
+/// indices of the (x, y, z) triples in `points` sorted along the Hilbert curve
+#[wasm_bindgen]
+pub fn hilbert3_order(n: u32, points: &[i32]) -> Result<Vec<u32>, JsError> {
+    let keys = hilbert3_keys(n, points)?;
+    let mut order: Vec<u32> = (0..keys.len() as u32).collect();
+    order.sort_by_key(|&i| keys[i as usize]);
+    Ok(order)
+}
+
+/// each cell of a `dims` dimensional curve in order, the cube has side n
+pub fn all_hilbert_nd_in(dims: usize, n: u32, amount: u64) -> impl Iterator<Item = Vec<u32>> {
+    let curve = HilbertNd::covering(dims, n);
+    (0..amount).map(move |d| {
+        let mut point = vec![0; dims];
+        curve.index_to_point(d, &mut point);
+        point
+    })
+}
+
+/// first `amount` cells of a `dims` dimensional curve, `dims` coordinates per cell
+#[wasm_bindgen]
+pub fn all_hilbert_nd(dims: usize, n: u32, amount: u32) -> Result<Vec<u32>, JsError> {
+    HilbertNd::try_covering(dims, n)?;
+    Ok(all_hilbert_nd_in(dims, n, amount as u64)
+        .flatten()
+        .collect())
+}
+
+/// Hilbert index of each point in `points`, `dims` coordinates per point
+#[wasm_bindgen]
+pub fn hilbert_nd_keys(dims: usize, n: u32, points: &[u32]) -> Result<Vec<u64>, JsError> {
+    let curve = HilbertNd::try_covering(dims, n)?;
+    Ok(points
+        .chunks_exact(dims)
+        .map(|p| curve.point_to_index(p))
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        all_hilbert3, d2xyz, hilbert3_keys, hilbert3_points, hilbert_nd_keys, key_curve, xyz2d,
+        CurveSizeError, HilbertNd,
+    };
+
+    fn assert_walks_cube(curve: HilbertNd) {
+        let cells = curve.side().pow(curve.dims() as u32);
+        let mut seen = vec![false; cells as usize];
+        let mut previous = vec![0; curve.dims()];
+        let mut point = vec![0; curve.dims()];
+        for d in 0..cells {
+            curve.index_to_point(d, &mut point);
+            let cell = point
+                .iter()
+                .rev()
+                .fold(0, |acc, &c| acc * curve.side() + c as u64);
+            assert!(!seen[cell as usize]);
+            seen[cell as usize] = true;
+            assert_eq!(curve.point_to_index(&point), d);
+            if d > 0 {
+                let step: i64 = point
+                    .iter()
+                    .zip(&previous)
+                    .map(|(&a, &b)| (a as i64 - b as i64).abs())
+                    .sum();
+                assert_eq!(step, 1, "{:?} d = {}", curve, d);
+            }
+            previous.copy_from_slice(&point);
+        }
+    }
+
+    #[test]
+    fn walks_every_cell_with_unit_steps() {
+        for (dims, bits) in [(1, 4), (2, 5), (3, 1), (3, 4), (4, 3), (5, 2)] {
+            assert_walks_cube(HilbertNd::new(dims, bits));
+        }
+    }
+
+    #[test]
+    fn wide_indices_round_trip() {
+        let curve = HilbertNd::new(3, 21);
+        let mut point = [0; 3];
+        for d in [0, 1, 12345678901, (1 << 63) - 1, u64::MAX >> 1] {
+            curve.index_to_point(d, &mut point);
+            assert_eq!(curve.point_to_index(&point), d);
+        }
+        let curve = HilbertNd::new(2, 32);
+        let mut point = [0; 2];
+        curve.index_to_point(u64::MAX, &mut point);
+        assert_eq!(curve.point_to_index(&point), u64::MAX);
+    }
+
+    #[test]
+    fn flat_buffers() {
+        assert_eq!(d2xyz(8, 0), (0, 0, 0));
+        let (x, y, z) = d2xyz(8, 511);
+        assert_eq!(xyz2d(8, x, y, z), 511);
+        let points = all_hilbert3(5, 512).unwrap();
+        assert_eq!(points.len(), 512 * 3);
+        let keys = hilbert3_keys(5, &points).unwrap();
+        assert_eq!(keys, (0..512).collect::<Vec<u32>>());
+        assert_eq!(hilbert3_points(5, &keys).unwrap(), points);
+        assert_eq!(hilbert_nd_keys(3, 5, &[0, 0, 0, 1, 0, 0]).unwrap(), [0, 1]);
+    }
+
+    #[test]
+    fn sizes_out_of_range() {
+        let error = |dims, bits, index_bits| {
+            Err(CurveSizeError {
+                dims,
+                bits,
+                index_bits,
+            })
+        };
+        assert_eq!(HilbertNd::try_new(0, 4), error(0, 4, 64));
+        assert_eq!(HilbertNd::try_new(1, 33), error(1, 33, 64));
+        assert_eq!(HilbertNd::try_new(3, 22), error(3, 22, 64));
+        assert_eq!(HilbertNd::try_covering(4, u32::MAX), error(4, 32, 64));
+        assert!(HilbertNd::try_covering(2, u32::MAX).is_ok());
+        //the 3D keys are 32 bit
+        assert!(key_curve(1024).is_ok());
+        assert_eq!(key_curve(1025), error(3, 11, 32));
+    }
+}
@@ -1,8 +1,15 @@
+pub mod ascii85;
+pub mod base32;
 #[allow(arithmetic_overflow)]
 pub mod base64;
+pub mod codec;
+pub mod curves;
+pub mod hex;
 pub mod hilbert;
 pub mod mandelbrot;
+pub mod output;
 pub mod particles;
+pub mod rng;
 pub mod wordleMod;
 
 #[cfg(target_arch = "wasm32")]
@@ -15,6 +22,9 @@ extern "C" {
     pub fn random() -> f64;
     #[wasm_bindgen(js_namespace = console)]
     pub fn log(s: &str);
+    /// milliseconds since the epoch
+    #[wasm_bindgen(js_namespace = Date)]
+    pub fn now() -> f64;
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -24,3 +34,10 @@ pub fn random() -> f64 {
 
 #[cfg(not(target_arch = "wasm32"))]
 pub fn log(_: &str) {}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0.0, |t| t.as_secs_f64() * 1000.0)
+}
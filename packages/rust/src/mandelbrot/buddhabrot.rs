@@ -0,0 +1,181 @@
+use super::fractal::{Fractal, Mandelbrot};
+use super::{Complex, Scale};
+use crate::rng::SeededRng;
+use wasm_bindgen::prelude::*;
+
+//the points sampled for c, every orbit that escapes starts in there
+const SAMPLE_REGION: [f64; 4] = [-2.0, 1.0, -1.5, 1.5];
+
+/// Orbit density renderer: random c are iterated and the points visited by the orbits
+/// that escape are counted per pixel. Each colour channel has its own iteration limit,
+/// the same limit everywhere gives the Buddhabrot, different ones the Nebulabrot.
+/// `sample` can be called repeatedly, the image sharpens as samples add up
+#[wasm_bindgen]
+pub struct Buddhabrot {
+    width: usize,
+    heigth: usize,
+    region: [f64; 4],
+    //iteration limit of the red, green and blue channels
+    limits: [u32; 3],
+    density: [Vec<u32>; 3],
+    rng: SeededRng,
+    samples: u64,
+}
+
+#[wasm_bindgen]
+impl Buddhabrot {
+    pub fn new(width: usize, heigth: usize, region: &[f64], seed: u32) -> Buddhabrot {
+        let region = match region {
+            [x0, x1, y0, y1] => [*x0, *x1, *y0, *y1],
+            _ => SAMPLE_REGION,
+        };
+        Buddhabrot {
+            width,
+            heigth,
+            region,
+            limits: [5000, 500, 50],
+            density: [0, 1, 2].map(|_| vec![0; width * heigth]),
+            rng: SeededRng::new(seed as u64),
+            samples: 0,
+        }
+    }
+
+    /// iteration limits of the channels, clears the density
+    pub fn set_limits(&mut self, red: u32, green: u32, blue: u32) {
+        self.limits = [red, green, blue];
+        self.density.iter_mut().for_each(|d| d.fill(0));
+        self.samples = 0;
+    }
+
+    /// iterates `count` more random points
+    pub fn sample(&mut self, count: u32) {
+        let limit = self.limits.iter().copied().max().unwrap_or(0);
+        let scale_x = Scale::new((self.region[0], self.region[1]), (0.0, self.width as f64));
+        let scale_y = Scale::new((self.region[3], self.region[2]), (0.0, self.heigth as f64));
+        let mut orbit = Vec::with_capacity(limit as usize);
+        for _ in 0..count {
+            let c = Complex {
+                r: SAMPLE_REGION[0] + self.rng.next_f64() * (SAMPLE_REGION[1] - SAMPLE_REGION[0]),
+                i: SAMPLE_REGION[2] + self.rng.next_f64() * (SAMPLE_REGION[3] - SAMPLE_REGION[2]),
+            };
+            self.samples += 1;
+            if Mandelbrot.known_interior(c) {
+                continue;
+            }
+            orbit.clear();
+            let mut z = Complex::ZERO;
+            let mut escaped = None;
+            for n in 0..limit {
+                z = Mandelbrot.step(z, c);
+                if z.module() > 4.0 {
+                    escaped = Some(n);
+                    break;
+                }
+                orbit.push(z);
+            }
+            let Some(escaped) = escaped else {
+                continue;
+            };
+            for (channel, &channel_limit) in self.limits.iter().enumerate() {
+                if escaped >= channel_limit {
+                    continue;
+                }
+                for z in &orbit {
+                    let x = scale_x.apply(z.r);
+                    let y = scale_y.apply(z.i);
+                    if x >= 0.0 && y >= 0.0 && x < self.width as f64 && y < self.heigth as f64 {
+                        self.density[channel][y as usize * self.width + x as usize] += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    /// points sampled since the last reset
+    pub fn samples(&self) -> f64 {
+        self.samples as f64
+    }
+
+    /// visits of each pixel by the orbits of `channel` (0 red, 1 green, 2 blue), row by row,
+    /// empty for any other channel
+    pub fn density(&self, channel: usize) -> Vec<u32> {
+        self.density.get(channel).cloned().unwrap_or_default()
+    }
+
+    /// RGBA image, each channel scaled by the square root of its density over its maximum
+    pub fn image(&self) -> Vec<u8> {
+        let mut image = vec![255u8; self.width * self.heigth * 4];
+        for (channel, density) in self.density.iter().enumerate() {
+            let max = density.iter().copied().max().unwrap_or(0).max(1) as f64;
+            for (pixel, &count) in image.chunks_exact_mut(4).zip(density) {
+                pixel[channel] = ((count as f64 / max).sqrt() * 255.0).round() as u8;
+            }
+        }
+        image
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Buddhabrot;
+    use crate::rng::SeededRng;
+
+    #[test]
+    fn rng_is_seeded() {
+        let mut a = SeededRng::new(7);
+        let mut b = SeededRng::new(7);
+        let first: Vec<u64> = (0..10).map(|_| a.next_u64()).collect();
+        assert_eq!(first, (0..10).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(SeededRng::new(8).next_u64(), first[0]);
+        let mean = (0..10000).map(|_| a.next_f64()).sum::<f64>() / 10000.0;
+        assert!((mean - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn reproducible_and_progressive() {
+        let region = [-2.0, 1.0, -1.5, 1.5];
+        let mut once = Buddhabrot::new(60, 60, &region, 42);
+        once.set_limits(200, 100, 20);
+        once.sample(4000);
+        let mut twice = Buddhabrot::new(60, 60, &region, 42);
+        twice.set_limits(200, 100, 20);
+        twice.sample(1500);
+        twice.sample(2500);
+        assert_eq!(twice.samples(), 4000.0);
+        for channel in 0..3 {
+            assert_eq!(once.density(channel), twice.density(channel));
+        }
+        assert_eq!(once.image(), twice.image());
+
+        let mut other = Buddhabrot::new(60, 60, &region, 43);
+        other.set_limits(200, 100, 20);
+        other.sample(4000);
+        assert_ne!(other.density(0), once.density(0));
+        assert_eq!(once.density(3), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn bands_nest() {
+        let mut buddhabrot = Buddhabrot::new(40, 40, &[-2.0, 1.0, -1.5, 1.5], 1);
+        buddhabrot.set_limits(300, 100, 30);
+        buddhabrot.sample(5000);
+        let total = |channel| {
+            buddhabrot
+                .density(channel)
+                .iter()
+                .map(|&c| c as u64)
+                .sum::<u64>()
+        };
+        //a longer limit keeps every orbit a shorter one keeps, and more
+        assert!(total(2) > 0);
+        assert!(total(0) >= total(1) && total(1) >= total(2));
+        let density = buddhabrot.density(0);
+        let image = buddhabrot.image();
+        let brightest = density.iter().enumerate().max_by_key(|p| p.1).unwrap().0;
+        assert_eq!(image[brightest * 4], 255);
+        //the set is symmetric, so is the density up to sampling noise
+        let top: u64 = density[..800].iter().map(|&c| c as u64).sum();
+        let bottom: u64 = density[800..].iter().map(|&c| c as u64).sum();
+        assert!((top as f64 / bottom as f64 - 1.0).abs() < 0.2);
+    }
+}
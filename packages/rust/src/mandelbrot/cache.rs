@@ -0,0 +1,445 @@
+use super::{paint_values, raw_values, Coloring, FractalParams, OutputMode, RenderOptions, Window};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use wasm_bindgen::prelude::*;
+
+const TILE_SIZE: usize = 64;
+const HALF: usize = TILE_SIZE / 2;
+//tiles hold the raw values, painted once the view is assembled
+const TILE_BYTES: usize = TILE_SIZE * TILE_SIZE * std::mem::size_of::<Option<f64>>();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct TileKey {
+    //hash of what the raw values depend on, see `settings_key`
+    settings: u64,
+    level: i32,
+    x: i64,
+    y: i64,
+}
+
+/// A `width` x `heigth` view centered on (center_x, center_y) at zoom `level`,
+/// where a pixel is 2^-level wide
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileView {
+    pub width: usize,
+    pub heigth: usize,
+    pub center_x: f64,
+    pub center_y: f64,
+    pub level: i32,
+}
+
+#[wasm_bindgen]
+impl TileView {
+    pub fn new(width: usize, heigth: usize, center_x: f64, center_y: f64, level: i32) -> TileView {
+        TileView {
+            width,
+            heigth,
+            center_x,
+            center_y,
+            level,
+        }
+    }
+}
+
+struct Entry {
+    values: Vec<Option<f64>>,
+    last_used: u64,
+}
+
+/// Tiles of the values behind `calc_set` on a grid fixed in the complex plane, so views
+/// at the same zoom level share them. At level l a pixel is 2^-l wide and tile (x, y) covers
+/// pixels [64 x, 64 x + 64) from the origin, rows going down from the real axis.
+/// Pixel (i, j) of level l is pixel (2 i, 2 j) of level l + 1, so a missing tile is taken
+/// from the four tiles of the next level when they are cached, and a quarter of it from
+/// the tile of the previous level. Views are painted once assembled, so histogram colouring
+/// covers the whole view. The least recently used tiles are dropped once `max_bytes` is exceeded
+#[wasm_bindgen]
+pub struct TileCache {
+    tiles: HashMap<TileKey, Entry>,
+    //last use of each cached tile, oldest first
+    usage: BTreeMap<u64, TileKey>,
+    clock: u64,
+    max_bytes: usize,
+    hits: u32,
+    misses: u32,
+    derived: u32,
+}
+
+#[wasm_bindgen]
+impl TileCache {
+    pub fn new(max_bytes: usize) -> TileCache {
+        TileCache {
+            tiles: HashMap::new(),
+            usage: BTreeMap::new(),
+            clock: 0,
+            max_bytes,
+            hits: 0,
+            misses: 0,
+            derived: 0,
+        }
+    }
+
+    /// RGBA image of `view`. The view is snapped to the pixel grid of the level, so panning reuses every tile still
+    /// visible and zooming by a power of two reuses the tiles of the levels around.
+    /// With auto iterations the limit changes with the level, levels then share nothing
+    pub fn calc_view(
+        &mut self,
+        view: &TileView,
+        fractal: &FractalParams,
+        options: &RenderOptions,
+    ) -> Vec<u8> {
+        let TileView {
+            width,
+            heigth,
+            center_x,
+            center_y,
+            level,
+        } = *view;
+        let pixel = pixel_size(level);
+        let left = (center_x / pixel - width as f64 / 2.0).round() as i64;
+        let top = (-center_y / pixel - heigth as f64 / 2.0).round() as i64;
+        //the auto limit follows the view, not the tiles, so every tile of a view matches
+        let region = [
+            left as f64 * pixel,
+            (left + width as i64) as f64 * pixel,
+            -(top + heigth as i64) as f64 * pixel,
+            -top as f64 * pixel,
+        ];
+        let mut options = options.clone();
+        options.iterations = options.iteration_limit(&region);
+        options.auto_iterations = false;
+        let settings = settings_key(fractal, &options);
+
+        let tile = TILE_SIZE as i64;
+        let mut values = vec![None; width * heigth];
+        for ty in top.div_euclid(tile)..=(top + heigth as i64 - 1).div_euclid(tile) {
+            for tx in left.div_euclid(tile)..=(left + width as i64 - 1).div_euclid(tile) {
+                let key = TileKey {
+                    settings,
+                    level,
+                    x: tx,
+                    y: ty,
+                };
+                let tile_values = self.tile(key, fractal, &options);
+                //copy the part of the tile inside the view
+                let x_start = (tx * tile).max(left);
+                let x_end = ((tx + 1) * tile).min(left + width as i64);
+                let row_len = (x_end - x_start) as usize;
+                for y in (ty * tile).max(top)..((ty + 1) * tile).min(top + heigth as i64) {
+                    let from = ((y - ty * tile) * tile + x_start - tx * tile) as usize;
+                    let to = ((y - top) * width as i64 + x_start - left) as usize;
+                    values[to..to + row_len].copy_from_slice(&tile_values[from..from + row_len]);
+                }
+            }
+        }
+        self.evict();
+        paint_values(&values, width, &region, &options, None)
+    }
+
+    /// number of cached tiles
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// bytes of values held by the cache
+    pub fn memory(&self) -> usize {
+        self.tiles.len() * TILE_BYTES
+    }
+
+    /// tiles found in the cache since it was created
+    pub fn hits(&self) -> u32 {
+        self.hits
+    }
+
+    /// tiles that had to be computed since the cache was created
+    pub fn misses(&self) -> u32 {
+        self.misses
+    }
+
+    /// tiles taken from the next level, or a quarter of them from the previous one
+    pub fn derived(&self) -> u32 {
+        self.derived
+    }
+
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+        self.usage.clear();
+    }
+}
+
+impl TileCache {
+    //values of the tile, derived or computed when missing
+    fn tile(
+        &mut self,
+        key: TileKey,
+        fractal: &FractalParams,
+        options: &RenderOptions,
+    ) -> &[Option<f64>] {
+        self.clock += 1;
+        let clock = self.clock;
+        if let Some(entry) = self.tiles.get(&key) {
+            self.usage.remove(&entry.last_used);
+            self.hits += 1;
+        } else {
+            let size = TILE_SIZE as f64 * pixel_size(key.level);
+            let region = [
+                key.x as f64 * size,
+                (key.x + 1) as f64 * size,
+                -(key.y + 1) as f64 * size,
+                -key.y as f64 * size,
+            ];
+            //subdivision fills pixels from their neighbours, which differ between levels
+            let derived = match options.subdivision {
+                true => None,
+                false => self
+                    .derive_from_children(key)
+                    .or_else(|| self.derive_from_parent(key, &region, fractal, options)),
+            };
+            let values = match derived {
+                Some(values) => {
+                    self.derived += 1;
+                    values
+                }
+                None => {
+                    self.misses += 1;
+                    let window = Window::full(TILE_SIZE, TILE_SIZE);
+                    raw_values(TILE_SIZE, TILE_SIZE, &region, fractal, options, window)
+                }
+            };
+            self.tiles.insert(
+                key,
+                Entry {
+                    values,
+                    last_used: clock,
+                },
+            );
+        }
+        self.usage.insert(clock, key);
+        let entry = self.tiles.get_mut(&key).unwrap();
+        entry.last_used = clock;
+        &entry.values
+    }
+
+    //every other pixel of the four tiles of the next level covering the tile
+    fn derive_from_children(&self, key: TileKey) -> Option<Vec<Option<f64>>> {
+        let child = |dx: i64, dy: i64| {
+            let child = TileKey {
+                level: key.level + 1,
+                x: key.x * 2 + dx,
+                y: key.y * 2 + dy,
+                ..key
+            };
+            self.tiles.get(&child).map(|entry| &entry.values)
+        };
+        let children = [child(0, 0)?, child(1, 0)?, child(0, 1)?, child(1, 1)?];
+        let mut values = Vec::with_capacity(TILE_SIZE * TILE_SIZE);
+        for j in 0..TILE_SIZE {
+            for i in 0..TILE_SIZE {
+                let (i, j) = (i * 2, j * 2);
+                let child = children[j / TILE_SIZE * 2 + i / TILE_SIZE];
+                values.push(child[j % TILE_SIZE * TILE_SIZE + i % TILE_SIZE]);
+            }
+        }
+        Some(values)
+    }
+
+    //the even pixels from the quarter of the tile of the previous level, the others computed
+    fn derive_from_parent(
+        &self,
+        key: TileKey,
+        region: &[f64],
+        fractal: &FractalParams,
+        options: &RenderOptions,
+    ) -> Option<Vec<Option<f64>>> {
+        let parent = TileKey {
+            level: key.level - 1,
+            x: key.x.div_euclid(2),
+            y: key.y.div_euclid(2),
+            ..key
+        };
+        let parent = &self.tiles.get(&parent)?.values;
+        let (x0, y0) = (
+            key.x.rem_euclid(2) as usize * HALF,
+            key.y.rem_euclid(2) as usize * HALF,
+        );
+        let mut values = vec![None; TILE_SIZE * TILE_SIZE];
+        for j in 0..HALF {
+            for i in 0..HALF {
+                values[j * 2 * TILE_SIZE + i * 2] = parent[(y0 + j) * TILE_SIZE + x0 + i];
+            }
+        }
+        for (x, y) in [(1, 0), (0, 1), (1, 1)] {
+            let window = Window {
+                x,
+                y,
+                columns: HALF,
+                rows: HALF,
+                step: 2,
+            };
+            let part = raw_values(TILE_SIZE, TILE_SIZE, region, fractal, options, window);
+            for (k, value) in part.into_iter().enumerate() {
+                let (i, j) = (x + k % HALF * 2, y + k / HALF * 2);
+                values[j * TILE_SIZE + i] = value;
+            }
+        }
+        Some(values)
+    }
+
+    fn evict(&mut self) {
+        while self.memory() > self.max_bytes {
+            let Some((_, key)) = self.usage.pop_first() else {
+                break;
+            };
+            self.tiles.remove(&key);
+        }
+    }
+}
+
+fn pixel_size(level: i32) -> f64 {
+    2f64.powi(-level)
+}
+
+//the colouring is applied once the view is assembled, only whether escape times are
+//smooth changes the raw values, and distance fields don't have any
+fn settings_key(fractal: &FractalParams, options: &RenderOptions) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    fractal.hash(&mut hasher);
+    options.iterations.hash(&mut hasher);
+    options.output.hash(&mut hasher);
+    let smooth = options.coloring != Coloring::EscapeTime;
+    (options.output == OutputMode::EscapeTime && smooth).hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TileCache, TileView, TILE_BYTES};
+    use crate::mandelbrot::{calc_set, Coloring, FractalParams, OutputMode, RenderOptions};
+
+    //calc_set of the view `calc_view` shows at `level`, from pixel (left, top)
+    fn expected(
+        width: usize,
+        heigth: usize,
+        left: i64,
+        top: i64,
+        level: i32,
+        options: &RenderOptions,
+    ) -> Vec<u8> {
+        let pixel = 2f64.powi(-level);
+        let region = [
+            left as f64 * pixel,
+            (left + width as i64) as f64 * pixel,
+            -(top + heigth as i64) as f64 * pixel,
+            -top as f64 * pixel,
+        ];
+        calc_set(width, heigth, &region, &FractalParams::default(), options)
+    }
+
+    #[test]
+    fn view_matches_calc_set() {
+        let fractal = FractalParams::default();
+        for coloring in [Coloring::EscapeTime, Coloring::Smooth, Coloring::Histogram] {
+            for output in [OutputMode::EscapeTime, OutputMode::Distance] {
+                let mut cache = TileCache::new(usize::MAX);
+                let mut options = RenderOptions::new();
+                options.coloring = coloring;
+                options.output = output;
+                options.auto_iterations = true;
+                //at level 7 a pixel is 1/128 wide, the view starts on pixel (-200, -150)
+                let image = cache.calc_view(
+                    &TileView::new(300, 200, -0.390625, 0.390625, 7),
+                    &fractal,
+                    &options,
+                );
+                assert_eq!(image, expected(300, 200, -200, -150, 7, &options));
+            }
+        }
+    }
+
+    #[test]
+    fn pans_and_zooms_reuse_tiles() {
+        let mut cache = TileCache::new(usize::MAX);
+        let fractal = FractalParams::default();
+        let mut options = RenderOptions::new();
+        options.coloring = Coloring::Histogram;
+        cache.calc_view(&TileView::new(128, 128, 0.0, 0.0, 6), &fractal, &options);
+        //the view is exactly 2 x 2 tiles
+        assert_eq!((cache.misses(), cache.hits()), (4, 0));
+
+        //a 10 pixel pan exposes one new column of tiles
+        let first = cache.calc_view(
+            &TileView::new(128, 128, 10.0 / 64.0, 0.0, 6),
+            &fractal,
+            &options,
+        );
+        assert_eq!((cache.misses(), cache.hits()), (6, 4));
+        let again = cache.calc_view(
+            &TileView::new(128, 128, 10.0 / 64.0, 0.0, 6),
+            &fractal,
+            &options,
+        );
+        assert_eq!(first, again);
+        assert_eq!(first, expected(128, 128, -54, -64, 6, &options));
+        assert_eq!(cache.misses(), 6);
+
+        //zooming in takes a quarter of every tile from the level it left
+        let zoomed = cache.calc_view(&TileView::new(128, 128, 0.0, 0.0, 7), &fractal, &options);
+        assert_eq!((cache.misses(), cache.derived()), (6, 4));
+        assert_eq!(zoomed, expected(128, 128, -64, -64, 7, &options));
+        //and going back finds the level as it was
+        cache.calc_view(&TileView::new(128, 128, 0.0, 0.0, 6), &fractal, &options);
+        assert_eq!((cache.misses(), cache.derived()), (6, 4));
+
+        //zooming out of a level seen all around takes every tile from it
+        cache.calc_view(&TileView::new(512, 512, 0.0, 0.0, 8), &fractal, &options);
+        let (misses, derived) = (cache.misses(), cache.derived());
+        let out = cache.calc_view(&TileView::new(256, 256, 0.0, 0.0, 7), &fractal, &options);
+        assert_eq!(cache.misses(), misses);
+        assert_eq!(cache.derived(), derived + 12);
+        assert_eq!(out, expected(256, 256, -128, -128, 7, &options));
+
+        //the colouring is applied to the whole view, smooth colourings share the tiles
+        let misses = cache.misses();
+        let mut recolored = options.clone();
+        recolored.coloring = Coloring::Smooth;
+        recolored.set_gradient(&[0.0, 255.0, 0.0, 0.0, 1.0, 0.0, 0.0, 255.0]);
+        let image = cache.calc_view(&TileView::new(128, 128, 0.0, 0.0, 6), &fractal, &recolored);
+        assert_eq!(cache.misses(), misses);
+        assert_eq!(image, expected(128, 128, -64, -64, 6, &recolored));
+
+        //other settings don't share tiles
+        recolored.coloring = Coloring::EscapeTime;
+        cache.calc_view(&TileView::new(128, 128, 0.0, 0.0, 6), &fractal, &recolored);
+        assert_eq!(cache.misses(), misses + 4);
+        let misses = cache.misses();
+        cache.calc_view(
+            &TileView::new(128, 128, 0.0, 0.0, 6),
+            &FractalParams::multibrot(3),
+            &options,
+        );
+        assert_eq!(cache.misses(), misses + 4);
+    }
+
+    #[test]
+    fn least_recently_used_tiles_are_evicted() {
+        let mut cache = TileCache::new(6 * TILE_BYTES);
+        let fractal = FractalParams::default();
+        let options = RenderOptions::new();
+        cache.calc_view(&TileView::new(128, 128, 0.0, 0.0, 6), &fractal, &options);
+        cache.calc_view(&TileView::new(128, 128, 2.0, 0.0, 6), &fractal, &options);
+        assert_eq!(cache.len(), 6);
+        assert!(cache.memory() <= 6 * TILE_BYTES);
+        //the last view is still cached, the two oldest tiles of the first one are gone
+        let misses = cache.misses();
+        cache.calc_view(&TileView::new(128, 128, 2.0, 0.0, 6), &fractal, &options);
+        assert_eq!(cache.misses(), misses);
+        cache.calc_view(&TileView::new(128, 128, 0.0, 0.0, 6), &fractal, &options);
+        assert_eq!(cache.misses(), misses + 2);
+    }
+}
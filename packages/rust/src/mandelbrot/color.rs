@@ -0,0 +1,189 @@
+use std::hash::{Hash, Hasher};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Coloring {
+    /// integer escape iteration, shows bands
+    #[default]
+    EscapeTime,
+    /// normalized iteration count, continuous across the bands
+    Smooth,
+    /// smooth iteration count mapped through its cumulative histogram,
+    /// so every colour covers about the same number of pixels
+    Histogram,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Stop {
+    position: f64,
+    color: [f64; 3],
+}
+
+/// Colours interpolated linearly between stops, the grey `sqrt` ramp when empty
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Gradient {
+    stops: Vec<Stop>,
+}
+
+impl Hash for Gradient {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for stop in &self.stops {
+            state.write_u64(stop.position.to_bits());
+            stop.color.iter().for_each(|c| state.write_u64(c.to_bits()));
+        }
+    }
+}
+
+impl Gradient {
+    /// stops flattened as (position, r, g, b), positions from 0 to 1 and channels from 0 to 255,
+    /// a trailing incomplete stop is ignored
+    pub fn new(stops: &[f64]) -> Self {
+        let mut stops: Vec<Stop> = stops
+            .chunks_exact(4)
+            .map(|s| Stop {
+                position: s[0],
+                color: [s[1], s[2], s[3]],
+            })
+            .collect();
+        stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+        Self { stops }
+    }
+
+    /// colour at `t`, 0 for the first escaping pixels and 1 for the slowest ones
+    pub fn at(&self, t: f64) -> [u8; 3] {
+        let t = t.clamp(0.0, 1.0);
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => {
+                let grey = ((1.0 - t).sqrt() * 255.0).round() as u8;
+                return [grey; 3];
+            }
+        };
+        let color = if t <= first.position {
+            first.color
+        } else if t >= last.position {
+            last.color
+        } else {
+            let next = self.stops.partition_point(|s| s.position <= t);
+            let (a, b) = (self.stops[next - 1], self.stops[next]);
+            let k = (t - a.position) / (b.position - a.position);
+            [0, 1, 2].map(|c| a.color[c] + (b.color[c] - a.color[c]) * k)
+        };
+        color.map(|c| c.round().clamp(0.0, 255.0) as u8)
+    }
+}
+
+/// RGBA image of escape values, `None` for points in the set which are painted black.
+/// `limit` is the iteration limit the values were computed with
+pub fn paint(
+    values: &[Option<f64>],
+    limit: u32,
+    coloring: Coloring,
+    gradient: &Gradient,
+) -> Vec<u8> {
+    paint_with(values, limit, coloring, gradient, None)
+}
+
+/// `paint` of a part of an image, histogram colouring using `cdf` when given,
+/// the histogram of the whole image, instead of the one of `values`
+pub(crate) fn paint_with(
+    values: &[Option<f64>],
+    limit: u32,
+    coloring: Coloring,
+    gradient: &Gradient,
+    cdf: Option<&Histogram>,
+) -> Vec<u8> {
+    let own;
+    let cdf = match (coloring, cdf) {
+        (Coloring::Histogram, Some(cdf)) => Some(cdf),
+        (Coloring::Histogram, None) => {
+            own = Histogram::new(values);
+            Some(&own)
+        }
+        _ => None,
+    };
+    let mut image = vec![255u8; values.len() * 4];
+    for (value, pixel) in values.iter().zip(image.chunks_exact_mut(4)) {
+        let color = match (value, &cdf) {
+            (None, _) => [0; 3],
+            (Some(value), Some(cdf)) => gradient.at(cdf.at(*value)),
+            (Some(value), None) => gradient.at(value / limit as f64),
+        };
+        pixel[..3].copy_from_slice(&color);
+    }
+    image
+}
+
+/// Fraction of the escaped pixels below each whole iteration count
+pub(crate) struct Histogram {
+    below: Vec<f64>,
+}
+
+impl Histogram {
+    pub fn new<'a>(values: impl IntoIterator<Item = &'a Option<f64>>) -> Self {
+        let bucket = |value: f64| value.max(0.0) as usize;
+        let escaped: Vec<f64> = values.into_iter().flatten().copied().collect();
+        let buckets = escaped.iter().map(|&v| bucket(v) + 2).max().unwrap_or(1);
+        let mut counts = vec![0usize; buckets];
+        escaped.iter().for_each(|&v| counts[bucket(v)] += 1);
+        let total = escaped.len().max(1) as f64;
+        let below = counts
+            .iter()
+            .scan(0, |sum, &count| {
+                let before = *sum;
+                *sum += count;
+                Some(before as f64 / total)
+            })
+            .collect();
+        Self { below }
+    }
+
+    fn at(&self, value: f64) -> f64 {
+        let value = value.max(0.0);
+        let bucket = value as usize;
+        //interpolate inside the bucket so the smooth value stays smooth
+        let (low, high) = (self.below[bucket], self.below[bucket + 1]);
+        low + (high - low) * value.fract()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{paint, Coloring, Gradient};
+
+    #[test]
+    fn gradient_interpolates() {
+        let grey = Gradient::default();
+        assert_eq!(grey.at(0.0), [255; 3]);
+        assert_eq!(grey.at(0.75), [128; 3]);
+        assert_eq!(grey.at(1.0), [0; 3]);
+
+        let gradient = Gradient::new(&[
+            1.0, 0.0, 0.0, 255.0, 0.0, 255.0, 0.0, 0.0, 0.5, 0.0, 255.0, 0.0,
+        ]);
+        assert_eq!(gradient.at(-1.0), [255, 0, 0]);
+        assert_eq!(gradient.at(0.25), [128, 128, 0]);
+        assert_eq!(gradient.at(0.5), [0, 255, 0]);
+        assert_eq!(gradient.at(0.75), [0, 128, 128]);
+        assert_eq!(gradient.at(2.0), [0, 0, 255]);
+    }
+
+    #[test]
+    fn histogram_spreads_colors() {
+        let values: Vec<Option<f64>> = [0.0, 0.0, 0.0, 1.0, 10.0, 40.0]
+            .iter()
+            .map(|&v| Some(v))
+            .chain([None])
+            .collect();
+        let gradient = Gradient::new(&[0.0, 0.0, 0.0, 0.0, 1.0, 240.0, 240.0, 240.0]);
+        let image = paint(&values, 50, Coloring::Histogram, &gradient);
+        let reds: Vec<u8> = image.chunks_exact(4).map(|p| p[0]).collect();
+        //three of the six escaped pixels are below count 1, four below 10, five below 40
+        assert_eq!(reds, [0, 0, 0, 120, 160, 200, 0]);
+        assert!(image.chunks_exact(4).all(|p| p[3] == 255));
+
+        let image = paint(&values, 50, Coloring::Smooth, &gradient);
+        assert_eq!(image[4 * 4], 48);
+    }
+}
@@ -0,0 +1,458 @@
+use super::color::paint;
+use super::fractal::Mandelbrot;
+use super::{escape_value, Complex, RenderOptions};
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+/// Unevaluated sum of two f64, about 32 significant digits.
+/// Enough to place a reference orbit at zooms f64 can't resolve
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct DoubleDouble {
+    pub hi: f64,
+    pub lo: f64,
+}
+
+//sum of a and b with the rounding error, exact
+fn two_sum(a: f64, b: f64) -> DoubleDouble {
+    let hi = a + b;
+    let b_virtual = hi - a;
+    let lo = (a - (hi - b_virtual)) + (b - b_virtual);
+    DoubleDouble { hi, lo }
+}
+
+//same as two_sum when |a| >= |b|
+fn quick_two_sum(a: f64, b: f64) -> DoubleDouble {
+    let hi = a + b;
+    DoubleDouble {
+        hi,
+        lo: b - (hi - a),
+    }
+}
+
+fn two_prod(a: f64, b: f64) -> DoubleDouble {
+    let hi = a * b;
+    DoubleDouble {
+        hi,
+        lo: a.mul_add(b, -hi),
+    }
+}
+
+impl DoubleDouble {
+    pub const ZERO: DoubleDouble = DoubleDouble { hi: 0.0, lo: 0.0 };
+
+    pub fn to_f64(self) -> f64 {
+        self.hi + self.lo
+    }
+}
+
+impl From<f64> for DoubleDouble {
+    fn from(hi: f64) -> Self {
+        DoubleDouble { hi, lo: 0.0 }
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = DoubleDouble;
+    fn add(self, rhs: Self) -> Self::Output {
+        let s = two_sum(self.hi, rhs.hi);
+        let t = two_sum(self.lo, rhs.lo);
+        let s = quick_two_sum(s.hi, s.lo + t.hi);
+        quick_two_sum(s.hi, s.lo + t.lo)
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = DoubleDouble;
+    fn neg(self) -> Self::Output {
+        DoubleDouble {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = DoubleDouble;
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + -rhs
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = DoubleDouble;
+    fn mul(self, rhs: Self) -> Self::Output {
+        let p = two_prod(self.hi, rhs.hi);
+        quick_two_sum(p.hi, p.lo + (self.hi * rhs.lo + self.lo * rhs.hi))
+    }
+}
+
+impl Div for DoubleDouble {
+    type Output = DoubleDouble;
+    fn div(self, rhs: Self) -> Self::Output {
+        //long division, one f64 of quotient at a time
+        let q1 = self.hi / rhs.hi;
+        let r = self - rhs * q1.into();
+        let q2 = r.hi / rhs.hi;
+        let r = r - rhs * q2.into();
+        let q3 = r.hi / rhs.hi;
+        quick_two_sum(q1, q2) + q3.into()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseNumberError {
+    /// byte where parsing stopped, the length of the input when it is empty or cut short
+    pub index: usize,
+}
+
+impl fmt::Display for ParseNumberError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid number at index {}", self.index)
+    }
+}
+
+impl std::error::Error for ParseNumberError {}
+
+/// decimal numbers like `-1.25`, `.5` or `3e-12`, keeping every digit f64 would round away
+impl FromStr for DoubleDouble {
+    type Err = ParseNumberError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let bytes = text.trim().as_bytes();
+        let error = |index| Err(ParseNumberError { index });
+        let (negative, mut index) = match bytes.first() {
+            Some(b'-') => (true, 1),
+            Some(b'+') => (false, 1),
+            _ => (false, 0),
+        };
+
+        let ten = DoubleDouble::from(10.0);
+        let mut mantissa = DoubleDouble::ZERO;
+        let mut exponent = 0i32;
+        let mut digits = 0;
+        let mut point = false;
+        while let Some(&byte) = bytes.get(index) {
+            match byte {
+                b'0'..=b'9' => {
+                    mantissa = mantissa * ten + DoubleDouble::from((byte - b'0') as f64);
+                    digits += 1;
+                    if point {
+                        exponent = match exponent.checked_sub(1) {
+                            Some(exponent) => exponent,
+                            None => return error(index),
+                        };
+                    }
+                }
+                b'.' if !point => point = true,
+                b'e' | b'E' if digits > 0 => break,
+                _ => return error(index),
+            }
+            index += 1;
+        }
+        if digits == 0 {
+            return error(index);
+        }
+        if bytes.get(index).is_some() {
+            let tail = std::str::from_utf8(&bytes[index + 1..]).unwrap_or("");
+            exponent = match tail
+                .parse::<i32>()
+                .ok()
+                .and_then(|e| exponent.checked_add(e))
+            {
+                Some(exponent) => exponent,
+                None => return error(index + 1),
+            };
+        }
+
+        let value = scale10(mantissa, exponent);
+        Ok(if negative { -value } else { value })
+    }
+}
+
+//`value` times 10^exponent, by factors of at most 10^300 so none leaves the f64 range
+//before the value does. `value` is 0 or at least 1, as a parsed mantissa
+fn scale10(mut value: DoubleDouble, mut exponent: i32) -> DoubleDouble {
+    let ten = DoubleDouble::from(10.0);
+    while exponent != 0 && value != DoubleDouble::ZERO {
+        let step = exponent.clamp(-300, 300);
+        let mut factor = DoubleDouble::from(1.0);
+        for _ in 0..step.unsigned_abs() {
+            factor = factor * ten;
+        }
+        value = match step < 0 {
+            true => value / factor,
+            false => value * factor,
+        };
+        //overflowing products come out as nan in double-double
+        if !value.hi.is_finite() {
+            return DoubleDouble::from(f64::INFINITY);
+        }
+        exponent -= step;
+    }
+    value
+}
+
+//orbit of the center in double-double, rounded to f64 once computed
+fn reference_orbit(center: (DoubleDouble, DoubleDouble), limit: u32) -> Vec<Complex> {
+    let (c_r, c_i) = center;
+    let (mut r, mut i) = (DoubleDouble::ZERO, DoubleDouble::ZERO);
+    let mut orbit = vec![Complex::ZERO];
+    for _ in 0..limit {
+        let r2 = r * r;
+        let i2 = i * i;
+        let ri = r * i;
+        r = r2 - i2 + c_r;
+        i = ri + ri + c_i;
+        let z = Complex {
+            r: r.to_f64(),
+            i: i.to_f64(),
+        };
+        orbit.push(z);
+        if z.module() > 4.0 {
+            break;
+        }
+    }
+    orbit
+}
+
+/// Escape time of the point `center + dc` of the Mandelbrot set by perturbation:
+/// only the difference `dz` to the reference orbit is iterated, in f64.
+/// When |z| gets smaller than |dz| the reference can't represent the orbit any more
+/// (a glitch), so the pixel restarts from the beginning of the reference with z as its delta
+fn perturbed_escape(orbit: &[Complex], dc: Complex, limit: u32, smooth: bool) -> Option<f64> {
+    let mut dz = Complex::ZERO;
+    let mut m = 0;
+    for n in 0..limit {
+        let reference = orbit[m];
+        let two_z = reference + reference;
+        dz = two_z * dz + dz * dz + dc;
+        m += 1;
+        let z = orbit[m] + dz;
+        if z.module() > 4.0 {
+            //past the escape the orbit no longer needs the reference
+            let c = orbit[1] + dc;
+            return Some(escape_value(&Mandelbrot, n, z, c, smooth));
+        }
+        if z.module() < dz.module() || m == orbit.len() - 1 {
+            dz = z;
+            m = 0;
+        }
+    }
+    None
+}
+
+/// Escape times of a `width` x `heigth` view of the Mandelbrot set centered on
+/// the decimal coordinates `center_r + i center_i`, `radius` being half its width.
+/// Pixels land where `calc_set` would put them for the same region
+pub fn deep_escape_times(
+    width: usize,
+    heigth: usize,
+    center_r: &str,
+    center_i: &str,
+    radius: f64,
+    options: &RenderOptions,
+) -> Result<Vec<Option<f64>>, ParseNumberError> {
+    let center = (center_r.parse()?, center_i.parse()?);
+    let limit = options.iteration_limit(&[-radius, radius, -radius, radius]);
+    let smooth = options.coloring != super::Coloring::EscapeTime;
+    let orbit = reference_orbit(center, limit);
+    let step = 2.0 * radius / width as f64;
+    let mut values = Vec::with_capacity(width * heigth);
+    for j in 0..heigth {
+        for i in 0..width {
+            let dc = Complex {
+                r: (i as f64 - width as f64 / 2.0) * step,
+                i: (heigth as f64 / 2.0 - j as f64) * step,
+            };
+            values.push(perturbed_escape(&orbit, dc, limit, smooth));
+        }
+    }
+    Ok(values)
+}
+
+/// `calc_set` for the Mandelbrot set past the precision of f64,
+/// the center is given as decimal strings and `radius` is half the width of the view
+#[wasm_bindgen]
+pub fn calc_set_deep(
+    width: usize,
+    heigth: usize,
+    center_r: &str,
+    center_i: &str,
+    radius: f64,
+    options: &RenderOptions,
+) -> Result<Vec<u8>, JsError> {
+    let values = deep_escape_times(width, heigth, center_r, center_i, radius, options)?;
+    let limit = options.iteration_limit(&[-radius, radius, -radius, radius]);
+    Ok(paint(&values, limit, options.coloring, &options.gradient))
+}
+
+/// `calc_iterations` for deep zooms, see `calc_set_deep`
+#[wasm_bindgen]
+pub fn calc_iterations_deep(
+    width: usize,
+    heigth: usize,
+    center_r: &str,
+    center_i: &str,
+    radius: f64,
+    options: &RenderOptions,
+) -> Result<Vec<f32>, JsError> {
+    let values = deep_escape_times(width, heigth, center_r, center_i, radius, options)?;
+    Ok(values
+        .into_iter()
+        .map(|value| value.map_or(-1.0, |v| v as f32))
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{deep_escape_times, DoubleDouble, ParseNumberError};
+    use crate::mandelbrot::{calc_iterations, FractalParams, RenderOptions};
+
+    fn dd(text: &str) -> DoubleDouble {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn double_double_arithmetic() {
+        let third = DoubleDouble::from(1.0) / DoubleDouble::from(3.0);
+        let one = third * DoubleDouble::from(3.0);
+        assert!((one - DoubleDouble::from(1.0)).to_f64().abs() < 1e-31);
+        //1 + 1e-20 is lost in f64 but kept here
+        let x = DoubleDouble::from(1.0) + DoubleDouble::from(1e-20);
+        assert_eq!((x - DoubleDouble::from(1.0)).to_f64(), 1e-20);
+
+        assert_eq!(dd("1.5"), DoubleDouble::from(1.5));
+        assert_eq!(dd("-.25"), DoubleDouble::from(-0.25));
+        assert_eq!(dd("+3e2"), DoubleDouble::from(300.0));
+        assert_eq!(dd(" 12E-1 ").hi, 1.2);
+        let long = dd("0.1000000000000000000000000000001");
+        assert!(((long - dd("0.1")).to_f64() - 1e-31).abs() < 1e-33);
+
+        assert_eq!(
+            "".parse::<DoubleDouble>(),
+            Err(ParseNumberError { index: 0 })
+        );
+        assert_eq!(
+            "-".parse::<DoubleDouble>(),
+            Err(ParseNumberError { index: 1 })
+        );
+        assert_eq!(
+            "1.2.3".parse::<DoubleDouble>(),
+            Err(ParseNumberError { index: 3 })
+        );
+        assert_eq!(
+            "1x".parse::<DoubleDouble>(),
+            Err(ParseNumberError { index: 1 })
+        );
+        assert_eq!(
+            "1e".parse::<DoubleDouble>(),
+            Err(ParseNumberError { index: 2 })
+        );
+    }
+
+    #[test]
+    fn extreme_exponents() {
+        assert_eq!(dd("1e-2147483648").hi, 0.0);
+        assert_eq!(dd("0.5e-2147483647").hi, 0.0);
+        assert_eq!(dd("-1e-400").hi, 0.0);
+        assert_eq!(dd("1e2147483647").hi, f64::INFINITY);
+        //the exponent alone leaves the f64 range, the value doesn't
+        //near the subnormals the low part underflows, leaving about f64 precision
+        for (text, value) in [
+            ("1000e-310", 1e-307),
+            ("0.0001e310", 1e306),
+            ("1e-310", 1e-310),
+        ] {
+            assert!((dd(text).hi / value - 1.0).abs() < 1e-12, "{}", text);
+        }
+        //the fraction digit takes the exponent one past i32::MIN
+        assert_eq!(
+            "0.5e-2147483648".parse::<DoubleDouble>(),
+            Err(ParseNumberError { index: 4 })
+        );
+        assert_eq!(
+            "1e2147483648".parse::<DoubleDouble>(),
+            Err(ParseNumberError { index: 2 })
+        );
+    }
+
+    #[test]
+    fn matches_direct_iteration_at_low_zoom() {
+        let mut options = RenderOptions::new();
+        options.iterations = 200;
+        let (width, heigth) = (40, 30);
+        //region of calc_set with the same center and radius
+        let (cx, cy, r) = (-0.75, 0.1, 0.02);
+        let region = [cx - r, cx + r, cy - r * 0.75, cy + r * 0.75];
+        let direct = calc_iterations(width, heigth, &region, &FractalParams::default(), &options);
+        let deep = deep_escape_times(width, heigth, "-0.75", "0.1", r, &options).unwrap();
+        let matching = direct
+            .iter()
+            .zip(&deep)
+            .filter(|(&a, b)| b.map_or(-1.0, |v| v as f32) == a)
+            .count();
+        //orbits that graze the bailout may land one iteration apart
+        assert!(matching * 100 >= direct.len() * 98, "{}", matching);
+    }
+
+    //escape time of center + dc iterated entirely in double-double
+    fn direct_escape(
+        center: (DoubleDouble, DoubleDouble),
+        dc: (f64, f64),
+        limit: u32,
+    ) -> Option<f64> {
+        let c_r = center.0 + dc.0.into();
+        let c_i = center.1 + dc.1.into();
+        let (mut r, mut i) = (DoubleDouble::ZERO, DoubleDouble::ZERO);
+        for n in 0..limit {
+            let ri = r * i;
+            r = r * r - i * i + c_r;
+            i = ri + ri + c_i;
+            if r.to_f64().powi(2) + i.to_f64().powi(2) > 4.0 {
+                return Some(n as f64);
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn resolves_past_f64() {
+        //i is on the boundary, with spirals around it at every scale
+        let (center_r, center_i) = ("0.000000000000000000003", "1.000000000000000000002");
+        let radius = 1e-20;
+        let (width, heigth) = (12, 12);
+        let mut options = RenderOptions::new();
+        options.auto_iterations = true;
+        let limit = options.iteration_limit(&[-radius, radius, -radius, radius]);
+        let deep = deep_escape_times(width, heigth, center_r, center_i, radius, &options).unwrap();
+
+        let center = (dd(center_r), dd(center_i));
+        let step = 2.0 * radius / width as f64;
+        let mut matching = 0;
+        for j in 0..heigth {
+            for i in 0..width {
+                let dc = (
+                    (i as f64 - width as f64 / 2.0) * step,
+                    (heigth as f64 / 2.0 - j as f64) * step,
+                );
+                if direct_escape(center, dc, limit) == deep[j * width + i] {
+                    matching += 1;
+                }
+            }
+        }
+        assert!(matching * 100 >= deep.len() * 95, "{}", matching);
+        let mut distinct: Vec<i64> = deep.iter().map(|v| v.map_or(-1, |v| v as i64)).collect();
+        distinct.sort();
+        distinct.dedup();
+        assert!(distinct.len() > 5);
+
+        //f64 can't tell the rows apart
+        let (x, y) = (center.0.to_f64(), center.1.to_f64());
+        let region = [x - radius, x + radius, y - radius, y + radius];
+        let flat = calc_iterations(width, heigth, &region, &FractalParams::default(), &options);
+        assert!(flat.chunks(width).all(|row| row == &flat[..width]));
+        assert!(deep_escape_times(4, 4, "abc", "0", 1.0, &options).is_err());
+    }
+}
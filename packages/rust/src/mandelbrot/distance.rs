@@ -0,0 +1,213 @@
+//! Per-pixel outputs other than the escape time: the distance to the set, estimated
+//! from the derivative of the orbit, and the period of the cycle interior orbits settle in.
+//! d = |z| ln|z| / |dz| is within a factor 2 of the true distance (Koebe), which is
+//! enough to draw filaments thinner than a pixel that escape-time sampling misses
+
+use super::color::Gradient;
+use super::fractal::Fractal;
+use super::{Complex, RenderOptions, Window, PERIOD_EPSILON2, SMOOTH_RADIUS2};
+use wasm_bindgen::prelude::*;
+
+/// What `calc_set` and `calc_field` compute for every pixel
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum OutputMode {
+    /// iterations before escaping, coloured as `RenderOptions::coloring` says
+    #[default]
+    EscapeTime,
+    /// estimated distance to the set in the units of the region, -1 inside.
+    /// Painted darker the closer a pixel is to the set, up to one pixel away
+    Distance,
+    /// period of the cycle an interior orbit settles in, 0 when it didn't settle
+    /// within the iteration limit, -1 outside. Painted through the gradient, the outside white
+    InteriorPeriod,
+}
+
+//squared distance under which an orbit is back where it was when looking for its period,
+//looser than the escape-time one since a slowly converging cycle still tells its period.
+//Orbits landing on a repelling cycle, which rounding pushes out eventually, count as interior
+const CYCLE_EPSILON2: f64 = 1e-20;
+//spreads consecutive periods over the gradient
+const GOLDEN: f64 = 0.618_033_988_749_895;
+
+//how the orbit of a pixel ended
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Orbit {
+    Escaped { distance: f64 },
+    Cycle(u32),
+    Bounded,
+}
+
+//z and its derivative are iterated together, the orbit is in a cycle once it comes
+//within `epsilon2` of a point saved at each power of two iteration like in `escape_time`
+fn follow(fractal: &impl Fractal, point: Complex, limit: u32, epsilon2: f64) -> Orbit {
+    let (mut z, c) = fractal.start(point);
+    let mut dz = fractal.start_derivative();
+    let (mut saved, mut saved_at) = (z, 0);
+    for n in 1..=limit {
+        dz = fractal.derivative(z, dz);
+        z = fractal.step(z, c);
+        if z.module() > 4.0 {
+            //the estimate gets better as |z| grows
+            for _ in 0..8 {
+                if z.module() >= SMOOTH_RADIUS2 {
+                    break;
+                }
+                dz = fractal.derivative(z, dz);
+                z = fractal.step(z, c);
+            }
+            let module = z.module().sqrt();
+            return Orbit::Escaped {
+                distance: module * module.ln() / dz.module().sqrt(),
+            };
+        }
+        let moved = Complex {
+            r: z.r - saved.r,
+            i: z.i - saved.i,
+        };
+        if moved.module() < epsilon2 {
+            return Orbit::Cycle(n - saved_at);
+        }
+        if n.is_power_of_two() {
+            (saved, saved_at) = (z, n);
+        }
+    }
+    Orbit::Bounded
+}
+
+/// value of the output mode of `options` for every pixel of `window`, row by row from the top,
+/// see `OutputMode` for what is returned, the escape time is left to `escape_times`
+pub(super) fn field<F: Fractal>(
+    fractal: &F,
+    width: usize,
+    heigth: usize,
+    region: &[f64],
+    options: &RenderOptions,
+    window: Window,
+) -> Vec<f32> {
+    let Some(point) = super::pixel_to_point(width, heigth, region, window) else {
+        return vec![];
+    };
+    let (width, heigth) = (window.columns, window.rows);
+    let limit = options.iteration_limit(region);
+    let mode = options.output;
+    //distances keep the escape times of `escape_time`
+    let epsilon2 = match mode {
+        OutputMode::InteriorPeriod => CYCLE_EPSILON2,
+        _ if options.periodicity => PERIOD_EPSILON2,
+        _ => 0.0,
+    };
+    let mut values = Vec::with_capacity(width * heigth);
+    for j in 0..heigth {
+        for i in 0..width {
+            let point = point(i, j);
+            //the known bulbs have periods 1 and 2 but those are left to the iteration
+            let orbit = if mode == OutputMode::Distance
+                && options.bulb_check
+                && fractal.known_interior(point)
+            {
+                Orbit::Bounded
+            } else {
+                follow(fractal, point, limit, epsilon2)
+            };
+            values.push(match (mode, orbit) {
+                (OutputMode::Distance, Orbit::Escaped { distance }) => distance as f32,
+                (OutputMode::Distance, _) => -1.0,
+                (_, Orbit::Escaped { .. }) => -1.0,
+                (_, Orbit::Cycle(period)) => period as f32,
+                (_, Orbit::Bounded) => 0.0,
+            });
+        }
+    }
+    values
+}
+
+/// RGBA image of a `field` of `mode`, `pixel_size` being the width of a pixel in the plane
+pub(super) fn paint_field(
+    field: &[f32],
+    mode: OutputMode,
+    pixel_size: f64,
+    gradient: &Gradient,
+) -> Vec<u8> {
+    let mut image = vec![255u8; field.len() * 4];
+    for (&value, pixel) in field.iter().zip(image.chunks_exact_mut(4)) {
+        let value = value as f64;
+        let color = match mode {
+            OutputMode::InteriorPeriod if value < 0.0 => [255; 3],
+            OutputMode::InteriorPeriod if value > 0.0 => gradient.at((value * GOLDEN).fract()),
+            OutputMode::InteriorPeriod => [0; 3],
+            _ if value < 0.0 => [0; 3],
+            _ => gradient.at(1.0 - (value / pixel_size).min(1.0)),
+        };
+        pixel[..3].copy_from_slice(&color);
+    }
+    image
+}
+
+#[cfg(test)]
+mod test {
+    use super::{follow, Orbit, CYCLE_EPSILON2};
+    use crate::mandelbrot::fractal::{BurningShip, Fractal, Julia, Mandelbrot, Multibrot, Tricorn};
+    use crate::mandelbrot::Complex;
+
+    fn c(r: f64, i: f64) -> Complex {
+        Complex { r, i }
+    }
+
+    fn distance(fractal: &impl Fractal, point: Complex) -> f64 {
+        match follow(fractal, point, 1000, 0.0) {
+            Orbit::Escaped { distance } => distance,
+            orbit => panic!("{:?} didn't escape", orbit),
+        }
+    }
+
+    fn orbit(r: f64, i: f64) -> Orbit {
+        follow(&Mandelbrot, c(r, i), 1000, CYCLE_EPSILON2)
+    }
+
+    #[test]
+    fn distance_is_within_a_factor_two() {
+        //left of -2 the closest point of the set is the tip of the antenna,
+        //further than about 1 the estimate overshoots
+        for k in 1..20 {
+            let x = -2.0 - k as f64 * 0.05;
+            let exact = -2.0 - x;
+            for estimate in [
+                distance(&Mandelbrot, c(x, 0.0)),
+                distance(&Multibrot { degree: 2 }, c(x, 0.0)),
+            ] {
+                assert!(estimate > exact / 2.0 && estimate < exact * 2.0, "{}", x);
+            }
+        }
+        //the filled Julia set of 0 is the unit disk
+        let disk = Julia { c: c(0.0, 0.0) };
+        for k in 1..30 {
+            let (radius, angle) = (1.0 + k as f64 * 0.07, k as f64);
+            let estimate = distance(&disk, c(radius * angle.cos(), radius * angle.sin()));
+            let exact = radius - 1.0;
+            assert!(
+                estimate > exact / 2.0 && estimate < exact * 2.0,
+                "{}",
+                radius
+            );
+        }
+        for point in [c(0.6, 0.6), c(-1.9, 0.3), c(0.3, -1.2)] {
+            assert!(distance(&BurningShip, point) > 0.0);
+            assert!(distance(&Tricorn, point) > 0.0);
+        }
+    }
+
+    #[test]
+    fn interior_periods() {
+        //centers of hyperbolic components of periods 1 to 4
+        assert_eq!(orbit(0.0, 0.0), Orbit::Cycle(1));
+        assert_eq!(orbit(-1.0, 0.0), Orbit::Cycle(2));
+        assert_eq!(orbit(-0.122_561_166, 0.744_861_767), Orbit::Cycle(3));
+        assert_eq!(orbit(-1.754_877_666, 0.0), Orbit::Cycle(3));
+        assert_eq!(orbit(-1.310_702_641, 0.0), Orbit::Cycle(4));
+        //away from the centers too
+        assert_eq!(orbit(-0.3, 0.2), Orbit::Cycle(1));
+        assert_eq!(orbit(-1.1, 0.1), Orbit::Cycle(2));
+        assert!(matches!(orbit(0.5, 0.0), Orbit::Escaped { .. }));
+    }
+}
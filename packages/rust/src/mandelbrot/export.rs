@@ -0,0 +1,490 @@
+//! PPM and PNG files of the RGBA images `calc_set` returns, so renders can be saved
+//! outside the browser. The PNG data is compressed with fixed Huffman codes and
+//! a small LZ77 matcher, or stored when that doesn't make it smaller
+
+use std::fmt;
+use wasm_bindgen::prelude::*;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+//lengths and distances of deflate, the base of each code and its extra bits
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+const WINDOW: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+//candidates tried for each match, more compress better and slower
+const MAX_CHAIN: usize = 64;
+const HASH_BITS: u32 = 15;
+
+static CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 == 1 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+};
+
+/// The RGBA buffer doesn't hold `width` x `heigth` pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageSizeError {
+    /// bytes `width` x `heigth` pixels take
+    pub expected: usize,
+    pub len: usize,
+}
+
+impl fmt::Display for ImageSizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected {} bytes of RGBA pixels, got {}",
+            self.expected, self.len
+        )
+    }
+}
+
+impl std::error::Error for ImageSizeError {}
+
+fn check_size(width: u32, heigth: u32, rgba: &[u8]) -> Result<(), ImageSizeError> {
+    let expected = width as usize * heigth as usize * 4;
+    match rgba.len() == expected {
+        true => Ok(()),
+        false => Err(ImageSizeError {
+            expected,
+            len: rgba.len(),
+        }),
+    }
+}
+
+/// binary PPM (P6), the alpha channel is dropped
+pub fn encode_ppm(width: u32, heigth: u32, rgba: &[u8]) -> Result<Vec<u8>, ImageSizeError> {
+    check_size(width, heigth, rgba)?;
+    let mut out = format!("P6\n{} {}\n255\n", width, heigth).into_bytes();
+    out.reserve(rgba.len() / 4 * 3);
+    rgba.chunks_exact(4)
+        .for_each(|pixel| out.extend_from_slice(&pixel[..3]));
+    Ok(out)
+}
+
+/// 8 bit RGBA PNG
+pub fn encode_png(width: u32, heigth: u32, rgba: &[u8]) -> Result<Vec<u8>, ImageSizeError> {
+    check_size(width, heigth, rgba)?;
+    //every row starts with its filter, none
+    let mut raw = Vec::with_capacity(rgba.len() + heigth as usize);
+    if width > 0 {
+        for row in rgba.chunks_exact(width as usize * 4) {
+            raw.push(0);
+            raw.extend_from_slice(row);
+        }
+    }
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&heigth.to_be_bytes());
+    //bit depth, RGBA, deflate, filters of the spec, no interlace
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut out = PNG_SIGNATURE.to_vec();
+    push_chunk(&mut out, b"IHDR", &header);
+    push_chunk(&mut out, b"IDAT", &zlib(&raw));
+    push_chunk(&mut out, b"IEND", &[]);
+    Ok(out)
+}
+
+/// `encode_png` for the browser, to download a render
+#[wasm_bindgen]
+pub fn png(width: u32, heigth: u32, rgba: &[u8]) -> Result<Vec<u8>, JsError> {
+    Ok(encode_png(width, heigth, rgba)?)
+}
+
+/// Writes `rgba` to `path` as PNG or PPM, after the extension of the path.
+/// Native only, in the browser the file system is unsupported
+pub fn save(path: &std::path::Path, width: u32, heigth: u32, rgba: &[u8]) -> std::io::Result<()> {
+    use std::io::{Error, ErrorKind};
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let bytes = match extension.to_ascii_lowercase().as_str() {
+        "png" => encode_png(width, heigth, rgba),
+        "ppm" => encode_ppm(width, heigth, rgba),
+        _ => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("unknown image format {:?}, expected png or ppm", extension),
+            ))
+        }
+    };
+    std::fs::write(
+        path,
+        bytes.map_err(|e| Error::new(ErrorKind::InvalidInput, e))?,
+    )
+}
+
+fn push_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &byte| {
+        CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+pub fn adler32(bytes: &[u8]) -> u32 {
+    //5552 bytes is the most that can be summed before the sums overflow
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in bytes.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    b << 16 | a
+}
+
+/// zlib stream of `data`
+pub fn zlib(data: &[u8]) -> Vec<u8> {
+    //deflate with a 32K window, no dictionary, fastest level
+    let mut out = vec![0x78, 0x01];
+    let compressed = deflate_fixed(data);
+    if compressed.len() < stored_len(data.len()) {
+        out.extend_from_slice(&compressed);
+    } else {
+        deflate_stored(data, &mut out);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn stored_len(len: usize) -> usize {
+    len + 5 * len.div_ceil(65535).max(1)
+}
+
+fn deflate_stored(data: &[u8], out: &mut Vec<u8>) {
+    let mut blocks = data.chunks(65535).peekable();
+    if data.is_empty() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        out.push(blocks.peek().is_none() as u8);
+        let len = block.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+}
+
+//deflate bits are packed from the least significant bit, Huffman codes from their first bit
+struct BitWriter {
+    out: Vec<u8>,
+    bits: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    fn bits(&mut self, value: u32, count: u32) {
+        self.bits |= (value as u64) << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.out.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn code(&mut self, code: u32, len: u32) {
+        self.bits(code.reverse_bits() >> (32 - len), len);
+    }
+
+    //fixed Huffman code of a literal, a length or the end of the block
+    fn symbol(&mut self, symbol: u32) {
+        match symbol {
+            0..=143 => self.code(0x30 + symbol, 8),
+            144..=255 => self.code(0x190 + symbol - 144, 9),
+            256..=279 => self.code(symbol - 256, 7),
+            _ => self.code(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.out.push(self.bits as u8);
+        }
+        self.out
+    }
+}
+
+//hash chains of the 3 byte sequences seen, positions are stored + 1 so 0 ends a chain
+struct Matcher {
+    head: Vec<usize>,
+    prev: Vec<usize>,
+}
+
+impl Matcher {
+    fn hash(data: &[u8], at: usize) -> usize {
+        let key = u32::from_le_bytes([data[at], data[at + 1], data[at + 2], 0]);
+        (key.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize
+    }
+
+    fn insert(&mut self, data: &[u8], at: usize) {
+        if at + MIN_MATCH <= data.len() {
+            let hash = Self::hash(data, at);
+            self.prev[at] = self.head[hash];
+            self.head[hash] = at + 1;
+        }
+    }
+
+    //length and distance of the longest match of what starts at `at` in the window
+    fn longest(&self, data: &[u8], at: usize) -> (usize, usize) {
+        let (mut best_len, mut best_distance) = (0, 0);
+        if at + MIN_MATCH > data.len() {
+            return (0, 0);
+        }
+        let max = MAX_MATCH.min(data.len() - at);
+        let mut candidate = self.head[Self::hash(data, at)];
+        for _ in 0..MAX_CHAIN {
+            if candidate == 0 || at - (candidate - 1) > WINDOW {
+                break;
+            }
+            let from = candidate - 1;
+            let len = (0..max)
+                .take_while(|&k| data[from + k] == data[at + k])
+                .count();
+            if len > best_len {
+                (best_len, best_distance) = (len, at - from);
+                if len == max {
+                    break;
+                }
+            }
+            candidate = self.prev[from];
+        }
+        (best_len, best_distance)
+    }
+}
+
+//a single block of fixed Huffman codes
+fn deflate_fixed(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter {
+        out: Vec::with_capacity(data.len() / 4),
+        bits: 0,
+        count: 0,
+    };
+    //last block, fixed codes
+    writer.bits(0b011, 3);
+    let mut matcher = Matcher {
+        head: vec![0; 1 << HASH_BITS],
+        prev: vec![0; data.len()],
+    };
+    let mut at = 0;
+    while at < data.len() {
+        let (len, distance) = matcher.longest(data, at);
+        if len >= MIN_MATCH {
+            let code = LENGTH_BASE.partition_point(|&base| base as usize <= len) - 1;
+            writer.symbol(257 + code as u32);
+            writer.bits(
+                (len - LENGTH_BASE[code] as usize) as u32,
+                LENGTH_EXTRA[code] as u32,
+            );
+            let code = DISTANCE_BASE.partition_point(|&base| base as usize <= distance) - 1;
+            writer.code(code as u32, 5);
+            writer.bits(
+                (distance - DISTANCE_BASE[code] as usize) as u32,
+                DISTANCE_EXTRA[code] as u32,
+            );
+            (at..at + len).for_each(|k| matcher.insert(data, k));
+            at += len;
+        } else {
+            writer.symbol(data[at] as u32);
+            matcher.insert(data, at);
+            at += 1;
+        }
+    }
+    writer.symbol(256);
+    writer.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{adler32, crc32, encode_png, encode_ppm, zlib, ImageSizeError};
+
+    struct Bits<'a> {
+        data: &'a [u8],
+        at: usize,
+    }
+
+    impl Bits<'_> {
+        fn read(&mut self, count: u32) -> u32 {
+            let mut value = 0;
+            for k in 0..count {
+                value |= ((self.data[self.at / 8] >> (self.at % 8)) as u32 & 1) << k;
+                self.at += 1;
+            }
+            value
+        }
+    }
+
+    //reads what `zlib` writes: stored blocks and blocks of fixed codes
+    fn inflate(stream: &[u8]) -> Vec<u8> {
+        let mut bits = Bits {
+            data: &stream[2..stream.len() - 4],
+            at: 0,
+        };
+        let mut out: Vec<u8> = vec![];
+        loop {
+            let last = bits.read(1);
+            match bits.read(2) {
+                0 => {
+                    let skip = (8 - bits.at % 8) % 8;
+                    bits.at += skip;
+                    let len = bits.read(16) as usize;
+                    assert_eq!(bits.read(16) as usize, !len & 0xffff);
+                    (0..len).for_each(|_| out.push(bits.read(8) as u8));
+                }
+                1 => loop {
+                    let mut code = (0..7).fold(0, |code, _| code << 1 | bits.read(1));
+                    let symbol = if code < 24 {
+                        code + 256
+                    } else {
+                        code = code << 1 | bits.read(1);
+                        match code {
+                            0x30..=0xbf => code - 0x30,
+                            0xc0..=0xc7 => code - 0xc0 + 280,
+                            _ => (code << 1 | bits.read(1)) - 0x190 + 144,
+                        }
+                    };
+                    match symbol {
+                        0..=255 => out.push(symbol as u8),
+                        256 => break,
+                        _ => {
+                            let code = symbol as usize - 257;
+                            let len = super::LENGTH_BASE[code] as usize
+                                + bits.read(super::LENGTH_EXTRA[code] as u32) as usize;
+                            let code = (0..5).fold(0, |code, _| code << 1 | bits.read(1)) as usize;
+                            let distance = super::DISTANCE_BASE[code] as usize
+                                + bits.read(super::DISTANCE_EXTRA[code] as u32) as usize;
+                            for _ in 0..len {
+                                out.push(out[out.len() - distance]);
+                            }
+                        }
+                    }
+                },
+                kind => panic!("block type {}", kind),
+            }
+            if last == 1 {
+                break;
+            }
+        }
+        assert_eq!(stream[..2], [0x78, 0x01]);
+        assert_eq!(stream[stream.len() - 4..], adler32(&out).to_be_bytes());
+        out
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        assert_eq!(adler32(b""), 1);
+        //past the point the sums are reduced at
+        let long = vec![0xffu8; 100_000];
+        let (a, b) = long.iter().fold((1u64, 0u64), |(a, b), &x| {
+            let a = (a + x as u64) % 65521;
+            (a, (b + a) % 65521)
+        });
+        assert_eq!(adler32(&long), (b << 16 | a) as u32);
+    }
+
+    #[test]
+    fn zlib_round_trip() {
+        let mut noise = 12345u32;
+        let random: Vec<u8> = (0..70_000)
+            .map(|_| {
+                noise ^= noise << 13;
+                noise ^= noise >> 17;
+                noise ^= noise << 5;
+                noise as u8
+            })
+            .collect();
+        let runs: Vec<u8> = (0..100_000u32).map(|i| (i / 300 % 7) as u8).collect();
+        let text = b"abcabcabcabcabcxyz the end of the text, the end of it".repeat(50);
+        for data in [&[][..], b"a", &random, &runs, &text] {
+            let stream = zlib(data);
+            assert_eq!(inflate(&stream), data);
+        }
+        //noise is stored in blocks of at most 64K, repetitions shrink
+        assert_eq!(zlib(&random).len(), 70_000 + 2 * 5 + 6);
+        assert!(zlib(&runs).len() < 2000);
+    }
+
+    #[test]
+    fn image_files() {
+        let rgba: Vec<u8> = (0..3 * 2).flat_map(|i| [i * 40, 7, 200, 255]).collect();
+        assert_eq!(
+            encode_ppm(3, 2, &rgba).unwrap(),
+            [
+                b"P6\n3 2\n255\n".to_vec(),
+                (0..6).flat_map(|i| [i * 40, 7, 200]).collect()
+            ]
+            .concat()
+        );
+        assert_eq!(
+            encode_png(3, 3, &rgba),
+            Err(ImageSizeError {
+                expected: 36,
+                len: 24
+            })
+        );
+
+        let png = encode_png(3, 2, &rgba).unwrap();
+        assert_eq!(
+            png[..8],
+            [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n']
+        );
+        //walk the chunks checking their crc
+        let mut chunks = vec![];
+        let mut at = 8;
+        while at < png.len() {
+            let len = u32::from_be_bytes(png[at..at + 4].try_into().unwrap()) as usize;
+            let body = &png[at + 4..at + 8 + len];
+            let crc = u32::from_be_bytes(png[at + 8 + len..at + 12 + len].try_into().unwrap());
+            assert_eq!(crc32(body), crc);
+            chunks.push((body[..4].to_vec(), body[4..].to_vec()));
+            at += 12 + len;
+        }
+        let kinds: Vec<&[u8]> = chunks.iter().map(|c| &c.0[..]).collect();
+        assert_eq!(kinds, [b"IHDR", b"IDAT", b"IEND"]);
+        assert_eq!(chunks[0].1, [0, 0, 0, 3, 0, 0, 0, 2, 8, 6, 0, 0, 0]);
+        let raw = inflate(&chunks[1].1);
+        assert_eq!(raw, [&[0], &rgba[..12], &[0], &rgba[12..]].concat());
+    }
+}
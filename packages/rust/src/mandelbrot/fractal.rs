@@ -0,0 +1,285 @@
+use super::Complex;
+use std::hash::{Hash, Hasher};
+use wasm_bindgen::prelude::*;
+
+/// An escape-time fractal: the orbit of a pixel starts at `start`
+/// and `step` is applied until it escapes
+pub trait Fractal {
+    /// true when `step` is z² + c, which the vector kernel can take over
+    const QUADRATIC: bool = false;
+    /// true when the set is connected without holes and holds the origin,
+    /// so subdivision can fill a rectangle from a uniform border
+    const CONNECTED: bool = false;
+
+    /// first z of the orbit and the constant c used by every step
+    fn start(&self, point: Complex) -> (Complex, Complex);
+    fn step(&self, z: Complex, c: Complex) -> Complex;
+    /// derivative of `step(z, c)` with respect to the pixel, `dz` being the one of `z`
+    fn derivative(&self, z: Complex, dz: Complex) -> Complex;
+    /// derivative of the first z with respect to the pixel
+    fn start_derivative(&self) -> Complex {
+        Complex::ZERO
+    }
+    /// how fast the orbit grows once it escaped, |z| goes to about |z|^degree each step
+    fn degree(&self) -> f64 {
+        2.0
+    }
+    /// `CONNECTED`, for families where it depends on the parameters
+    fn connected(&self) -> bool {
+        Self::CONNECTED
+    }
+    /// points that are certainly in the set and don't need iterating
+    fn known_interior(&self, _point: Complex) -> bool {
+        false
+    }
+}
+
+const ONE: Complex = Complex { r: 1.0, i: 0.0 };
+const TWO: Complex = Complex { r: 2.0, i: 0.0 };
+
+/// z² + c from z = 0, c the pixel
+pub struct Mandelbrot;
+
+/// z² + c from z = the pixel, c fixed
+pub struct Julia {
+    pub c: Complex,
+}
+
+/// (|re z| + i|im z|)² + c
+pub struct BurningShip;
+
+/// conj(z)² + c, also called the Mandelbar set
+pub struct Tricorn;
+
+/// z^degree + c
+pub struct Multibrot {
+    pub degree: u32,
+}
+
+impl Fractal for Mandelbrot {
+    const QUADRATIC: bool = true;
+    const CONNECTED: bool = true;
+
+    fn start(&self, point: Complex) -> (Complex, Complex) {
+        (Complex::ZERO, point)
+    }
+
+    fn step(&self, z: Complex, c: Complex) -> Complex {
+        z * z + c
+    }
+
+    fn derivative(&self, z: Complex, dz: Complex) -> Complex {
+        TWO * z * dz + ONE
+    }
+
+    fn known_interior(&self, point: Complex) -> bool {
+        let (x, y2) = (point.r, point.i * point.i);
+        //main cardioid
+        let q = (x - 0.25) * (x - 0.25) + y2;
+        let cardioid = q * (q + (x - 0.25)) < y2 / 4.0;
+        //period 2 bulb, the disk of radius 1/4 around -1
+        let bulb = (x + 1.0) * (x + 1.0) + y2 < 1.0 / 16.0;
+        cardioid || bulb
+    }
+}
+
+impl Fractal for Julia {
+    const QUADRATIC: bool = true;
+
+    fn start(&self, point: Complex) -> (Complex, Complex) {
+        (point, self.c)
+    }
+
+    fn step(&self, z: Complex, c: Complex) -> Complex {
+        z * z + c
+    }
+
+    //c is fixed, the pixel is the first z
+    fn derivative(&self, z: Complex, dz: Complex) -> Complex {
+        TWO * z * dz
+    }
+
+    fn start_derivative(&self) -> Complex {
+        ONE
+    }
+
+    //the Julia set is connected when c is in the Mandelbrot set
+    fn connected(&self) -> bool {
+        let mut z = Complex::ZERO;
+        (0..1000).all(|_| {
+            z = z * z + self.c;
+            z.module() <= 4.0
+        })
+    }
+}
+
+impl Fractal for BurningShip {
+    fn start(&self, point: Complex) -> (Complex, Complex) {
+        (Complex::ZERO, point)
+    }
+
+    fn step(&self, z: Complex, c: Complex) -> Complex {
+        let z = Complex {
+            r: z.r.abs(),
+            i: z.i.abs(),
+        };
+        z * z + c
+    }
+
+    //the folds flip the components of dz along with the ones of z
+    fn derivative(&self, z: Complex, dz: Complex) -> Complex {
+        let dz = Complex {
+            r: dz.r * z.r.signum(),
+            i: dz.i * z.i.signum(),
+        };
+        let z = Complex {
+            r: z.r.abs(),
+            i: z.i.abs(),
+        };
+        TWO * z * dz + ONE
+    }
+}
+
+impl Fractal for Tricorn {
+    fn start(&self, point: Complex) -> (Complex, Complex) {
+        (Complex::ZERO, point)
+    }
+
+    fn step(&self, z: Complex, c: Complex) -> Complex {
+        let z = z.conj();
+        z * z + c
+    }
+
+    fn derivative(&self, z: Complex, dz: Complex) -> Complex {
+        TWO * z.conj() * dz.conj() + ONE
+    }
+}
+
+impl Fractal for Multibrot {
+    const CONNECTED: bool = true;
+
+    fn start(&self, point: Complex) -> (Complex, Complex) {
+        (Complex::ZERO, point)
+    }
+
+    fn step(&self, z: Complex, c: Complex) -> Complex {
+        z.powi(self.degree) + c
+    }
+
+    fn derivative(&self, z: Complex, dz: Complex) -> Complex {
+        let degree = Complex {
+            r: self.degree as f64,
+            i: 0.0,
+        };
+        degree * z.powi(self.degree.saturating_sub(1)) * dz + ONE
+    }
+
+    fn degree(&self) -> f64 {
+        //below 2 the orbit doesn't grow geometrically, keep the smooth count finite
+        self.degree.max(2) as f64
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FractalKind {
+    Mandelbrot,
+    Julia,
+    BurningShip,
+    Tricorn,
+    Multibrot,
+}
+
+/// Which fractal `calc_set` draws, with the parameters of the families that have one
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FractalParams {
+    pub kind: FractalKind,
+    /// c of the Julia set
+    pub c_r: f64,
+    pub c_i: f64,
+    /// exponent of the Multibrot set
+    pub degree: u32,
+}
+
+#[wasm_bindgen]
+impl FractalParams {
+    pub fn new(kind: FractalKind) -> FractalParams {
+        FractalParams {
+            kind,
+            c_r: 0.0,
+            c_i: 0.0,
+            degree: 2,
+        }
+    }
+
+    pub fn julia(c_r: f64, c_i: f64) -> FractalParams {
+        FractalParams {
+            c_r,
+            c_i,
+            ..FractalParams::new(FractalKind::Julia)
+        }
+    }
+
+    pub fn multibrot(degree: u32) -> FractalParams {
+        FractalParams {
+            degree,
+            ..FractalParams::new(FractalKind::Multibrot)
+        }
+    }
+}
+
+impl Hash for FractalParams {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.kind.hash(state);
+        state.write_u64(self.c_r.to_bits());
+        state.write_u64(self.c_i.to_bits());
+        self.degree.hash(state);
+    }
+}
+
+impl Default for FractalParams {
+    fn default() -> Self {
+        FractalParams::new(FractalKind::Mandelbrot)
+    }
+}
+
+/// Runs `$body` with `$f` bound to the `Fractal` described by `$params`,
+/// so the iteration loop is compiled once per family instead of dispatching every step
+macro_rules! with_fractal {
+    ($params:expr, $f:ident => $body:expr) => {{
+        use $crate::mandelbrot::fractal::*;
+        let params: &FractalParams = $params;
+        match params.kind {
+            FractalKind::Mandelbrot => {
+                let $f = Mandelbrot;
+                $body
+            }
+            FractalKind::Julia => {
+                let $f = Julia {
+                    c: $crate::mandelbrot::Complex {
+                        r: params.c_r,
+                        i: params.c_i,
+                    },
+                };
+                $body
+            }
+            FractalKind::BurningShip => {
+                let $f = BurningShip;
+                $body
+            }
+            FractalKind::Tricorn => {
+                let $f = Tricorn;
+                $body
+            }
+            FractalKind::Multibrot => {
+                let $f = Multibrot {
+                    degree: params.degree,
+                };
+                $body
+            }
+        }
+    }};
+}
+
+pub(crate) use with_fractal;
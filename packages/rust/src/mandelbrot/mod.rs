@@ -0,0 +1,834 @@
+pub mod buddhabrot;
+pub mod cache;
+pub mod color;
+pub mod deep;
+pub mod distance;
+pub mod export;
+pub mod fractal;
+pub mod renderer;
+pub mod simd;
+mod subdivision;
+
+use color::{paint, paint_with, Gradient, Histogram};
+use fractal::{with_fractal, Fractal};
+use std::ops::{Add, Mul};
+use wasm_bindgen::prelude::*;
+
+pub use buddhabrot::Buddhabrot;
+pub use cache::{TileCache, TileView};
+pub use color::Coloring;
+pub use deep::{DoubleDouble, ParseNumberError};
+pub use distance::OutputMode;
+pub use fractal::{FractalKind, FractalParams};
+pub use renderer::{MandelbrotRenderer, RegionError, Tile};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Complex {
+    pub r: f64,
+    pub i: f64,
+}
+
+impl Complex {
+    pub const ZERO: Complex = Complex { r: 0.0, i: 0.0 };
+
+    fn module(&self) -> f64 {
+        self.i * self.i + self.r * self.r
+    }
+
+    fn conj(self) -> Complex {
+        Complex {
+            r: self.r,
+            i: -self.i,
+        }
+    }
+
+    //exponentiation by squaring
+    fn powi(self, mut exponent: u32) -> Complex {
+        let mut base = self;
+        let mut result = Complex { r: 1.0, i: 0.0 };
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exponent >>= 1;
+        }
+        result
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+    fn mul(self, rhs: Self) -> Self::Output {
+        Complex {
+            r: self.r * rhs.r - self.i * rhs.i,
+            i: self.r * rhs.i + self.i * rhs.r,
+        }
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+    fn add(self, rhs: Self) -> Self::Output {
+        Complex {
+            r: self.r + rhs.r,
+            i: self.i + rhs.i,
+        }
+    }
+}
+
+const N: u32 = 50;
+//width of the region the auto iteration limit starts growing from, the whole set
+const FULL_VIEW: f64 = 3.0;
+
+//|z|² the orbit is followed to before taking the smooth iteration count
+const SMOOTH_RADIUS2: f64 = 1e6;
+//squared distance under which an orbit is taken to have come back to a point it visited
+const PERIOD_EPSILON2: f64 = 1e-30;
+
+/// Iteration at which the orbit of `point` leaves the radius 2 disk, None if it never does.
+/// When `smooth` the orbit is followed a bit further and the normalized iteration count
+/// n + 1 - ln(ln |z|) / ln(degree) is returned instead, continuous across bands.
+/// With `periodicity` the orbit stops early when it comes back to a point it already
+/// visited: it is then caught in a cycle and never escapes. The orbit is compared
+/// to a point saved at each power of two iteration (Brent)
+fn escape_time(
+    fractal: &impl Fractal,
+    point: &Complex,
+    limit: u32,
+    smooth: bool,
+    periodicity: bool,
+) -> Option<f64> {
+    let (mut acc, c) = fractal.start(*point);
+    let mut saved = acc;
+    let mut next_save = 1;
+    for i in 0..limit {
+        acc = fractal.step(acc, c);
+        if acc.module() > 4.0 {
+            return Some(escape_value(fractal, i, acc, c, smooth));
+        }
+        if periodicity {
+            let distance = Complex {
+                r: acc.r - saved.r,
+                i: acc.i - saved.i,
+            };
+            if distance.module() < PERIOD_EPSILON2 {
+                return None;
+            }
+            if i + 1 == next_save {
+                saved = acc;
+                next_save *= 2;
+            }
+        }
+    }
+    None
+}
+
+//escape value of an orbit that left the disk at iteration `i` with `acc`
+fn escape_value(fractal: &impl Fractal, i: u32, mut acc: Complex, c: Complex, smooth: bool) -> f64 {
+    if !smooth {
+        return i as f64;
+    }
+    let mut n = i;
+    while acc.module() < SMOOTH_RADIUS2 && n < i + 8 {
+        acc = fractal.step(acc, c);
+        n += 1;
+    }
+    let log_z = acc.module().ln() / 2.0;
+    n as f64 + 1.0 - log_z.ln() / fractal.degree().ln()
+}
+
+struct Scale {
+    alpha: f64,
+    k: f64,
+}
+
+impl Scale {
+    fn apply(&self, x: f64) -> f64 {
+        self.alpha * x + self.k
+    }
+    fn new(from: (f64, f64), to: (f64, f64)) -> Scale {
+        let alpha = (to.1 - to.0) / (from.1 - from.0);
+        let k = to.0 - from.0 * alpha;
+        Scale { alpha, k }
+    }
+}
+
+/// Iteration limit that keeps the boundary detailed at the zoom of `region`:
+/// 50 for the whole set and 100 more for every tenfold zoom
+#[wasm_bindgen]
+pub fn auto_iterations(region: &[f64]) -> u32 {
+    match region {
+        [x0, x1, _, _] if x1 != x0 => {
+            let zoom = FULL_VIEW / (x1 - x0).abs();
+            (N as f64 + 100.0 * zoom.log10().max(0.0)).min(1e6) as u32
+        }
+        _ => N,
+    }
+}
+
+/// How `calc_set` iterates and turns escape times into colours
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOptions {
+    pub coloring: Coloring,
+    gradient: Gradient,
+    /// iteration limit, used when `auto_iterations` is off
+    pub iterations: u32,
+    /// derive the iteration limit from the zoom of the region instead
+    pub auto_iterations: bool,
+    /// iterate two pixels at once where the fractal allows it, same output as the scalar loop
+    pub simd: bool,
+    /// skip the points of the main cardioid and the period 2 bulb, known to be in the set
+    pub bulb_check: bool,
+    /// stop iterating orbits that settled in a cycle
+    pub periodicity: bool,
+    /// fill rectangles with a uniform border without iterating them (Mariani–Silver)
+    pub subdivision: bool,
+    /// what is computed for every pixel, escape time, distance to the set or interior period
+    pub output: OutputMode,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            coloring: Coloring::default(),
+            gradient: Gradient::default(),
+            iterations: N,
+            auto_iterations: false,
+            simd: true,
+            bulb_check: true,
+            periodicity: true,
+            subdivision: false,
+            output: OutputMode::default(),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl RenderOptions {
+    pub fn new() -> RenderOptions {
+        Self::default()
+    }
+
+    /// the iteration limit used to draw `region`
+    pub fn iteration_limit(&self, region: &[f64]) -> u32 {
+        if self.auto_iterations {
+            auto_iterations(region)
+        } else {
+            self.iterations
+        }
+    }
+
+    /// stops flattened as (position, r, g, b), positions from 0 to 1 and channels from 0 to 255,
+    /// no stops brings back the grey ramp
+    pub fn set_gradient(&mut self, stops: &[f64]) {
+        self.gradient = Gradient::new(stops);
+    }
+}
+
+#[wasm_bindgen]
+pub fn calc_set(
+    width: usize,
+    heigth: usize,
+    region: &[f64],
+    fractal: &FractalParams,
+    options: &RenderOptions,
+) -> Vec<u8> {
+    let window = Window::full(width, heigth);
+    let values = raw_values(width, heigth, region, fractal, options, window);
+    paint_values(&values, width, region, options, None)
+}
+
+/// Raw escape iteration of every pixel, row by row from the top, -1 for points in the set.
+/// Smooth unless `options.coloring` is `EscapeTime`, so it can be coloured again without iterating
+#[wasm_bindgen]
+pub fn calc_iterations(
+    width: usize,
+    heigth: usize,
+    region: &[f64],
+    fractal: &FractalParams,
+    options: &RenderOptions,
+) -> Vec<f32> {
+    let window = Window::full(width, heigth);
+    with_fractal!(fractal, f => escape_times(&f, width, heigth, region, options, window))
+        .into_iter()
+        .map(|value| value.map_or(-1.0, |v| v as f32))
+        .collect()
+}
+
+/// RGBA image of the output of `calc_iterations`, `limit` being the iteration limit it used
+#[wasm_bindgen]
+pub fn color_iterations(iterations: &[f32], limit: u32, options: &RenderOptions) -> Vec<u8> {
+    let values: Vec<Option<f64>> = iterations
+        .iter()
+        .map(|&v| (v >= 0.0).then_some(v as f64))
+        .collect();
+    paint(&values, limit, options.coloring, &options.gradient)
+}
+
+/// Raw value of the output mode of `options` for every pixel, row by row from the top,
+/// `calc_iterations` for `OutputMode::EscapeTime`
+#[wasm_bindgen]
+pub fn calc_field(
+    width: usize,
+    heigth: usize,
+    region: &[f64],
+    fractal: &FractalParams,
+    options: &RenderOptions,
+) -> Vec<f32> {
+    match options.output {
+        OutputMode::EscapeTime => calc_iterations(width, heigth, region, fractal, options),
+        _ => {
+            let window = Window::full(width, heigth);
+            with_fractal!(fractal, f => distance::field(&f, width, heigth, region, options, window))
+        }
+    }
+}
+
+/// RGBA image of the output of `calc_field` for an image `width` pixels wide showing `region`
+#[wasm_bindgen]
+pub fn color_field(
+    field: &[f32],
+    width: usize,
+    region: &[f64],
+    options: &RenderOptions,
+) -> Vec<u8> {
+    match (options.output, region) {
+        (OutputMode::EscapeTime, _) => {
+            color_iterations(field, options.iteration_limit(region), options)
+        }
+        (mode, [x0, x1, _, _]) => {
+            let pixel_size = (x1 - x0).abs() / width.max(1) as f64;
+            distance::paint_field(field, mode, pixel_size, &options.gradient)
+        }
+        _ => vec![],
+    }
+}
+
+/// Pixels `step` apart of a view, `columns` x `rows` of them from pixel (x, y).
+/// Parts of a view computed through a window land on the points the whole view uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Window {
+    pub x: usize,
+    pub y: usize,
+    pub columns: usize,
+    pub rows: usize,
+    pub step: usize,
+}
+
+impl Window {
+    pub fn full(width: usize, heigth: usize) -> Window {
+        Window {
+            x: 0,
+            y: 0,
+            columns: width,
+            rows: heigth,
+            step: 1,
+        }
+    }
+}
+
+/// Raw value of every pixel of `window` of the view, row by row: the escape time, None
+/// inside, or the field of the output mode of `options`. The iteration limit is the view's
+pub(crate) fn raw_values(
+    width: usize,
+    heigth: usize,
+    region: &[f64],
+    fractal: &FractalParams,
+    options: &RenderOptions,
+    window: Window,
+) -> Vec<Option<f64>> {
+    match options.output {
+        OutputMode::EscapeTime => with_fractal!(fractal, f => {
+            escape_times(&f, width, heigth, region, options, window)
+        }),
+        _ => with_fractal!(fractal, f => {
+            distance::field(&f, width, heigth, region, options, window)
+        })
+        .into_iter()
+        .map(|value| Some(value as f64))
+        .collect(),
+    }
+}
+
+/// RGBA image of `raw_values` of a view `width` pixels wide showing `region`.
+/// Histogram colouring uses `cdf` when given, so parts of a view share the one of the view
+pub(crate) fn paint_values(
+    values: &[Option<f64>],
+    width: usize,
+    region: &[f64],
+    options: &RenderOptions,
+    cdf: Option<&Histogram>,
+) -> Vec<u8> {
+    match (options.output, region) {
+        (OutputMode::EscapeTime, _) => {
+            let limit = options.iteration_limit(region);
+            paint_with(values, limit, options.coloring, &options.gradient, cdf)
+        }
+        (mode, [x0, x1, _, _]) => {
+            let field: Vec<f32> = values.iter().map(|v| v.unwrap_or(-1.0) as f32).collect();
+            let pixel_size = (x1 - x0).abs() / width.max(1) as f64;
+            distance::paint_field(&field, mode, pixel_size, &options.gradient)
+        }
+        _ => vec![],
+    }
+}
+
+//maps pixel (c, r) of `window` to its point of the plane, None when `region` isn't [x0, x1, y0, y1]
+fn pixel_to_point(
+    width: usize,
+    heigth: usize,
+    region: &[f64],
+    window: Window,
+) -> Option<impl Fn(usize, usize) -> Complex> {
+    let [x0, x1, y0, y1] = region else {
+        return None;
+    };
+    let scale_x = Scale::new((0f64, width as f64), (*x0, *x1));
+    let scale_y = Scale::new((heigth as f64, 0f64), (*y0, *y1));
+    Some(move |c: usize, r: usize| Complex {
+        r: scale_x.apply((window.x + c * window.step) as f64),
+        i: scale_y.apply((window.y + r * window.step) as f64),
+    })
+}
+
+//escape time of every pixel of `window`, row by row from the top
+fn escape_times<F: Fractal>(
+    fractal: &F,
+    width: usize,
+    heigth: usize,
+    region: &[f64],
+    options: &RenderOptions,
+    window: Window,
+) -> Vec<Option<f64>> {
+    let smooth = options.coloring != Coloring::EscapeTime;
+    let limit = options.iteration_limit(region);
+    if let Some(point) = pixel_to_point(width, heigth, region, window) {
+        let (width, heigth) = (window.columns, window.rows);
+        let interior = |point: Complex| options.bulb_check && fractal.known_interior(point);
+        let pixel = |i: usize, j: usize| {
+            let point = point(i, j);
+            if interior(point) {
+                return None;
+            }
+            escape_time(fractal, &point, limit, smooth, options.periodicity)
+        };
+        if options.subdivision && fractal.connected() {
+            //the connected sets all hold the origin, so a rectangle around it may enclose one
+            let holds_origin = |x0, y0, x1, y1| {
+                let (a, b) = (point(x0, y0), point(x1, y1));
+                a.r.min(b.r) <= 0.0
+                    && 0.0 <= a.r.max(b.r)
+                    && a.i.min(b.i) <= 0.0
+                    && 0.0 <= a.i.max(b.i)
+            };
+            return subdivision::subdivide(width, heigth, pixel, holds_origin);
+        }
+
+        let mut values = Vec::with_capacity(width * heigth);
+        let pairs = if options.simd && F::QUADRATIC {
+            width / 2
+        } else {
+            0
+        };
+        for j in 0..heigth {
+            for pair in 0..pairs {
+                let points = [0, 1].map(|k| point(pair * 2 + k, j));
+                let known = points.map(interior);
+                if known == [true; 2] {
+                    values.extend([None, None]);
+                    continue;
+                }
+                let starts = points.map(|p| fractal.start(p));
+                let escaped = simd::escape_pair(
+                    starts.map(|s| s.0),
+                    starts.map(|s| s.1),
+                    limit,
+                    options.periodicity,
+                );
+                for (k, escape) in escaped.into_iter().enumerate() {
+                    let escape = escape.filter(|_| !known[k]);
+                    values.push(
+                        escape.map(|(n, z)| escape_value(fractal, n, z, starts[k].1, smooth)),
+                    );
+                }
+            }
+            for i in pairs * 2..width {
+                values.push(pixel(i, j));
+            }
+        }
+        return values;
+    }
+    vec![]
+}
+
+#[cfg(test)]
+mod test {
+    use super::fractal::{BurningShip, Fractal, Julia, Mandelbrot, Multibrot, Tricorn};
+    use super::{
+        auto_iterations, calc_field, calc_iterations, calc_set, color_field, color_iterations,
+        escape_time, Coloring, Complex, FractalKind, FractalParams, OutputMode, RenderOptions, N,
+    };
+
+    fn calc_score(fractal: &impl Fractal, point: &Complex) -> f64 {
+        escape_time(fractal, point, N, false, false).map_or(1.0, |i| i / N as f64)
+    }
+
+    fn c(r: f64, i: f64) -> Complex {
+        Complex { r, i }
+    }
+
+    #[test]
+    fn sanity() {
+        assert_eq!(
+            16,
+            calc_set(
+                2,
+                2,
+                &[1.0, 1.0, 1.0, 1.0],
+                &FractalParams::default(),
+                &RenderOptions::new()
+            )
+            .len()
+        );
+    }
+
+    #[test]
+    fn complex_arithmetic() {
+        assert_eq!(c(1.0, 2.0) * c(3.0, 4.0), c(-5.0, 10.0));
+        assert_eq!(c(0.0, 1.0) * c(0.0, 1.0), c(-1.0, 0.0));
+        assert_eq!(c(1.0, 1.0).powi(4), c(-4.0, 0.0));
+        assert_eq!(c(1.0, 2.0).powi(3), c(1.0, 2.0) * c(1.0, 2.0) * c(1.0, 2.0));
+    }
+
+    #[test]
+    fn fractal_membership() {
+        //period 2 bulb and the tip of the antenna are in the set, 0.5 is not
+        assert_eq!(calc_score(&Mandelbrot, &c(-1.0, 0.0)), 1.0);
+        assert_eq!(calc_score(&Mandelbrot, &c(-2.0, 0.0)), 1.0);
+        assert!(calc_score(&Mandelbrot, &c(0.5, 0.0)) < 1.0);
+        //only a correct multiplication keeps i on its period 2 orbit
+        assert_eq!(calc_score(&Mandelbrot, &c(0.0, 1.0)), 1.0);
+        assert!(calc_score(&Mandelbrot, &c(0.0, 1.1)) < 1.0);
+
+        //with c = 0 the Julia set is the unit disk
+        let disk = Julia { c: c(0.0, 0.0) };
+        assert_eq!(calc_score(&disk, &c(0.6, 0.7)), 1.0);
+        assert!(calc_score(&disk, &c(0.8, 0.7)) < 1.0);
+
+        //the burning ship is the mandelbrot set on the real line left of 0
+        assert_eq!(calc_score(&BurningShip, &c(-1.5, 0.0)), 1.0);
+        assert!(calc_score(&BurningShip, &c(0.5, 0.0)) < 1.0);
+        //and the tricorn is symmetric under conjugation
+        for point in [c(-0.2, 0.7), c(0.3, 0.6), c(-1.1, 0.2)] {
+            assert_eq!(
+                calc_score(&Tricorn, &point),
+                calc_score(&Tricorn, &point.conj())
+            );
+        }
+
+        for point in [c(-0.75, 0.1), c(0.3, 0.5), c(-1.3, 0.05)] {
+            assert_eq!(
+                calc_score(&Multibrot { degree: 2 }, &point),
+                calc_score(&Mandelbrot, &point)
+            );
+        }
+        assert!(calc_score(&Multibrot { degree: 3 }, &c(-1.0, 0.0)) < 1.0);
+        assert_eq!(calc_score(&Multibrot { degree: 3 }, &c(0.0, 0.5)), 1.0);
+    }
+
+    #[test]
+    fn escape_time_grey_ramp() {
+        let region = [-2.0, 1.0, -1.5, 1.5];
+        let image = calc_set(
+            6,
+            4,
+            &region,
+            &FractalParams::default(),
+            &RenderOptions::new(),
+        );
+        let greys: Vec<u8> = image.chunks_exact(4).map(|p| p[0]).collect();
+        //pixel (i, j) is at (-2 + i / 2, 1.5 - 3 j / 4)
+        assert_eq!(
+            greys,
+            [
+                255, 255, 252, 252, 252, 252, 255, 250, 250, 242, 149, 250, 0, 0, 0, 0, 0, 245,
+                255, 250, 250, 242, 149, 250
+            ]
+        );
+        assert!(image
+            .chunks_exact(4)
+            .all(|p| p[0] == p[1] && p[1] == p[2] && p[3] == 255));
+    }
+
+    #[test]
+    fn smooth_is_continuous() {
+        let fractal = super::fractal::Mandelbrot;
+        //walking outward along the real axis the smooth count decreases without jumps
+        //while the integer count drops by whole bands
+        let values: Vec<f64> = (0..1500)
+            .map(|k| {
+                escape_time(&fractal, &c(0.5 + k as f64 * 0.001, 0.0), N, true, false).unwrap()
+            })
+            .collect();
+        for pair in values.windows(2) {
+            assert!(pair[1] < pair[0] && pair[0] - pair[1] < 0.05);
+        }
+        let banded = escape_time(&fractal, &c(0.4, 0.0), N, false, false).unwrap();
+        let smooth = escape_time(&fractal, &c(0.4, 0.0), N, true, false).unwrap();
+        assert!((smooth - banded).abs() < 1.5);
+
+        let mut options = RenderOptions::new();
+        options.coloring = Coloring::Smooth;
+        options.set_gradient(&[0.0, 0.0, 0.0, 255.0, 1.0, 255.0, 255.0, 0.0]);
+        let image = calc_set(
+            4,
+            4,
+            &[-2.0, 1.0, -1.5, 1.5],
+            &FractalParams::default(),
+            &options,
+        );
+        assert_eq!(image.len(), 64);
+    }
+
+    #[test]
+    fn iteration_limits() {
+        assert_eq!(auto_iterations(&[-2.0, 1.0, -1.5, 1.5]), 50);
+        assert_eq!(auto_iterations(&[-2.0, 4.0, -3.0, 3.0]), 50);
+        assert_eq!(auto_iterations(&[0.0, 3e-3, 0.0, 3e-3]), 350);
+        assert_eq!(auto_iterations(&[]), 50);
+
+        //a point escaping after 60 iterations is only resolved with a higher limit
+        let point = c(0.2501, 0.0);
+        let slow = escape_time(&Mandelbrot, &point, 1000, false, false).unwrap();
+        assert!(slow > 60.0);
+        assert_eq!(escape_time(&Mandelbrot, &point, N, false, false), None);
+
+        let region = [0.2501, 0.2503, -0.0001, 0.0002];
+        let mut options = RenderOptions::new();
+        assert_eq!(options.iteration_limit(&region), 50);
+        let shallow = calc_iterations(3, 3, &region, &FractalParams::default(), &options);
+        options.auto_iterations = true;
+        assert_eq!(options.iteration_limit(&region), 467);
+        let deep = calc_iterations(3, 3, &region, &FractalParams::default(), &options);
+        assert!(shallow.iter().all(|&v| v == -1.0));
+        assert!(deep.iter().any(|&v| v > 50.0));
+        options.auto_iterations = false;
+        options.iterations = 467;
+        assert_eq!(
+            calc_iterations(3, 3, &region, &FractalParams::default(), &options),
+            deep
+        );
+    }
+
+    #[test]
+    fn raw_iterations_recolor() {
+        let region = [-2.0, 1.0, -1.5, 1.5];
+        let fractal = FractalParams::default();
+        for coloring in [Coloring::EscapeTime, Coloring::Smooth, Coloring::Histogram] {
+            let mut options = RenderOptions::new();
+            options.coloring = coloring;
+            options.set_gradient(&[
+                0.0, 0.0, 10.0, 80.0, 0.4, 250.0, 250.0, 250.0, 1.0, 250.0, 120.0, 0.0,
+            ]);
+            let iterations = calc_iterations(16, 12, &region, &fractal, &options);
+            assert_eq!(iterations.len(), 16 * 12);
+            let recolored = color_iterations(&iterations, N, &options);
+            let image = calc_set(16, 12, &region, &fractal, &options);
+            //f32 rounding may move a channel by one
+            assert_eq!(recolored.len(), image.len());
+            assert!(recolored
+                .iter()
+                .zip(&image)
+                .all(|(a, b)| a.abs_diff(*b) <= 1));
+        }
+    }
+
+    #[test]
+    fn simd_matches_scalar() {
+        let region = [-2.0, 1.0, -1.2, 1.2];
+        for fractal in [
+            FractalParams::default(),
+            FractalParams::julia(-0.8, 0.156),
+            FractalParams::new(FractalKind::Tricorn),
+        ] {
+            for coloring in [Coloring::EscapeTime, Coloring::Smooth] {
+                let mut options = RenderOptions::new();
+                options.coloring = coloring;
+                options.iterations = 200;
+                let vector = calc_iterations(61, 40, &region, &fractal, &options);
+                options.simd = false;
+                let scalar = calc_iterations(61, 40, &region, &fractal, &options);
+                assert_eq!(vector, scalar);
+            }
+        }
+    }
+
+    #[test]
+    fn speedups_keep_the_output() {
+        let views = [
+            [-2.0, 1.0, -1.2, 1.2],
+            [-0.76, -0.73, 0.09, 0.12],
+            [-1.3, -1.1, -0.1, 0.1],
+            //the whole set inside an escaping border
+            [-4.0, 4.0, -4.0, 4.0],
+            [-3.0, 3.0, -3.0, 3.0],
+            //and off center, so a quarter of the view encloses it
+            [-12.0, 36.0, -6.0, 42.0],
+        ];
+        for region in views {
+            for coloring in [Coloring::EscapeTime, Coloring::Smooth] {
+                let mut plain = RenderOptions::new();
+                plain.coloring = coloring;
+                plain.iterations = 300;
+                plain.bulb_check = false;
+                plain.periodicity = false;
+                let fractal = FractalParams::default();
+                let expected = calc_iterations(97, 80, &region, &fractal, &plain);
+                for toggle in 0..3 {
+                    for simd in [false, true] {
+                        let mut options = plain.clone();
+                        options.simd = simd;
+                        match toggle {
+                            0 => options.bulb_check = true,
+                            1 => options.periodicity = true,
+                            _ => options.subdivision = true,
+                        }
+                        let values = calc_iterations(97, 80, &region, &fractal, &options);
+                        assert_eq!(values, expected, "{:?} {} {}", region, toggle, simd);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn subdivision_needs_a_connected_set() {
+        assert!(Julia { c: c(-0.12, 0.75) }.connected());
+        assert!(!Julia { c: c(-0.8, 0.2) }.connected());
+        assert!(!BurningShip.connected());
+
+        //a uniform border around the burning ship's antenna hides a pixel of it
+        for (fractal, region) in [
+            (
+                FractalParams::new(FractalKind::BurningShip),
+                [-1.8, -1.7, -0.08, 0.0],
+            ),
+            (FractalParams::julia(-0.8, 0.2), [-1.6, 1.6, -1.2, 1.2]),
+            (
+                FractalParams::new(FractalKind::Tricorn),
+                [-2.0, 1.0, -1.5, 1.5],
+            ),
+        ] {
+            let mut options = RenderOptions::new();
+            options.coloring = Coloring::EscapeTime;
+            options.iterations = 300;
+            let plain = calc_iterations(400, 300, &region, &fractal, &options);
+            options.subdivision = true;
+            let subdivided = calc_iterations(400, 300, &region, &fractal, &options);
+            assert_eq!(subdivided, plain, "{:?}", fractal.kind);
+        }
+    }
+
+    #[test]
+    fn known_interior() {
+        for point in [
+            c(0.0, 0.0),
+            c(-0.5, 0.5),
+            c(0.24, 0.0),
+            c(-1.0, 0.2),
+            c(-1.2, 0.0),
+        ] {
+            assert!(Mandelbrot.known_interior(point));
+            assert_eq!(calc_score(&Mandelbrot, &point), 1.0);
+        }
+        for point in [c(0.26, 0.0), c(-0.75, 0.1), c(-1.0, 0.3), c(-1.3, 0.0)] {
+            assert!(!Mandelbrot.known_interior(point));
+        }
+        assert!(!Julia { c: c(0.0, 0.0) }.known_interior(c(0.0, 0.0)));
+    }
+
+    #[test]
+    fn params_select_the_fractal() {
+        let region = [-2.0, 1.0, -1.5, 1.5];
+        let mandelbrot = calc_set(
+            8,
+            8,
+            &region,
+            &FractalParams::default(),
+            &RenderOptions::new(),
+        );
+        let multibrot = calc_set(
+            8,
+            8,
+            &region,
+            &FractalParams::multibrot(2),
+            &RenderOptions::new(),
+        );
+        assert_eq!(mandelbrot, multibrot);
+        let tricorn = calc_set(
+            8,
+            8,
+            &region,
+            &FractalParams::new(FractalKind::Tricorn),
+            &RenderOptions::new(),
+        );
+        assert_ne!(mandelbrot, tricorn);
+        let julia = FractalParams::julia(-0.8, 0.156);
+        assert_eq!(julia.kind, FractalKind::Julia);
+        assert_ne!(
+            calc_set(8, 8, &region, &julia, &RenderOptions::new()),
+            mandelbrot
+        );
+    }
+
+    #[test]
+    fn output_modes() {
+        let region = [-2.0, 1.0, -1.2, 1.2];
+        let fractal = FractalParams::default();
+        let mut options = RenderOptions::new();
+        options.iterations = 300;
+        let iterations = calc_iterations(60, 48, &region, &fractal, &options);
+        assert_eq!(calc_field(60, 48, &region, &fractal, &options), iterations);
+
+        //distances see the same pixels escape as the escape time does, periods a few less:
+        //-i lands on a repelling cycle and only escapes after rounding drifted it away
+        for output in [OutputMode::Distance, OutputMode::InteriorPeriod] {
+            options.output = output;
+            let field = calc_field(60, 48, &region, &fractal, &options);
+            assert_eq!(field.len(), iterations.len());
+            for (value, iteration) in field.iter().zip(&iterations) {
+                let interior = match output {
+                    OutputMode::Distance => *value < 0.0,
+                    _ => *value >= 0.0,
+                };
+                if output == OutputMode::Distance || *iteration < 0.0 {
+                    assert_eq!(interior, *iteration < 0.0);
+                }
+            }
+            let image = calc_set(60, 48, &region, &fractal, &options);
+            assert_eq!(image, color_field(&field, 60, &region, &options));
+        }
+
+        options.output = OutputMode::InteriorPeriod;
+        let periods = calc_field(60, 48, &region, &fractal, &options);
+        //the main cardioid and the period 2 bulb
+        assert_eq!(periods[24 * 60 + 40], 1.0);
+        assert_eq!(periods[24 * 60 + 20], 2.0);
+
+        //pixels within a pixel of the set are shaded, the others white
+        options.output = OutputMode::Distance;
+        let distances = calc_field(60, 48, &region, &fractal, &options);
+        let image = calc_set(60, 48, &region, &fractal, &options);
+        assert_eq!(image[(24 * 60 + 40) * 4], 0);
+        let mut shaded = 0;
+        for (distance, pixel) in distances.iter().zip(image.chunks_exact(4)) {
+            if *distance >= 0.0 {
+                assert_eq!(pixel[0] < 255, *distance < 0.05, "{}", distance);
+                shaded += (pixel[0] < 255) as usize;
+            }
+        }
+        assert!(shaded > 20, "{}", shaded);
+    }
+}
@@ -0,0 +1,409 @@
+use super::color::Histogram;
+use super::{paint_values, raw_values, Coloring, FractalParams, OutputMode, RenderOptions, Window};
+use crate::now;
+use std::collections::VecDeque;
+use std::fmt;
+use wasm_bindgen::prelude::*;
+
+//side in pixels of the blocks the first pass computes one pixel for
+const COARSE_SCALE: usize = 8;
+const DEFAULT_TILE_SIZE: usize = 64;
+
+/// A finished part of the image, ready to be drawn at (x, y) of the canvas.
+/// Coarse tiles are already scaled up to their full size
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    /// side of the blocks of identical pixels, 1 once refined
+    pub scale: usize,
+    /// the viewport the tile was rendered for, see `MandelbrotRenderer::viewport`
+    pub viewport: u32,
+    pixels: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl Tile {
+    /// RGBA pixels, `width` x `height`
+    pub fn pixels(&self) -> Vec<u8> {
+        self.pixels.clone()
+    }
+}
+
+impl Tile {
+    pub fn rgba(&self) -> &[u8] {
+        &self.pixels
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Job {
+    x: usize,
+    y: usize,
+    width: usize,
+    heigth: usize,
+    scale: usize,
+}
+
+/// Renders `calc_set` tile by tile without blocking: a coarse pass over the whole
+/// viewport first, then every tile at full resolution, nearest to the focus first.
+/// `render` works until its time budget runs out and JS pulls the results with `next_tile`.
+/// Refined tiles are the pixels `calc_set` gives for the whole viewport: the iteration
+/// limit is the one of the viewport, and with histogram colouring the tiles of a pass are
+/// held back until the pass is done, then painted with the histogram of the whole pass
+/// A viewport region that isn't the 4 numbers [x0, x1, y0, y1]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegionError {
+    /// numbers the region had
+    pub len: usize,
+}
+
+impl fmt::Display for RegionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected a region [x0, x1, y0, y1], got {} numbers",
+            self.len
+        )
+    }
+}
+
+impl std::error::Error for RegionError {}
+
+fn view_region(region: &[f64]) -> Result<[f64; 4], RegionError> {
+    match region {
+        [x0, x1, y0, y1] => Ok([*x0, *x1, *y0, *y1]),
+        _ => Err(RegionError { len: region.len() }),
+    }
+}
+
+#[wasm_bindgen]
+pub struct MandelbrotRenderer {
+    width: usize,
+    heigth: usize,
+    region: [f64; 4],
+    fractal: FractalParams,
+    options: RenderOptions,
+    //`options` with the iteration limit of the viewport
+    view_options: RenderOptions,
+    tile_size: usize,
+    viewport: u32,
+    pending: VecDeque<Job>,
+    //tiles of the current pass waiting for its histogram
+    held: Vec<(Job, Vec<Option<f64>>)>,
+    finished: VecDeque<Tile>,
+}
+
+#[wasm_bindgen]
+impl MandelbrotRenderer {
+    pub fn new(fractal: &FractalParams, options: &RenderOptions) -> MandelbrotRenderer {
+        MandelbrotRenderer {
+            width: 0,
+            heigth: 0,
+            region: [0.0; 4],
+            fractal: *fractal,
+            options: options.clone(),
+            view_options: options.clone(),
+            tile_size: DEFAULT_TILE_SIZE,
+            viewport: 0,
+            pending: VecDeque::new(),
+            held: vec![],
+            finished: VecDeque::new(),
+        }
+    }
+
+    pub fn set_tile_size(&mut self, tile_size: usize) {
+        self.tile_size = tile_size.max(1);
+    }
+
+    /// id of the current viewport, tiles rendered for earlier ones are never returned
+    pub fn viewport(&self) -> u32 {
+        self.viewport
+    }
+
+    /// Starts over on a new view, cancelling every tile of the previous one.
+    /// A region that isn't [x0, x1, y0, y1] is an error and keeps the current view
+    pub fn set_viewport(
+        &mut self,
+        width: usize,
+        heigth: usize,
+        region: &[f64],
+    ) -> Result<(), JsError> {
+        self.region = view_region(region)?;
+        self.width = width;
+        self.heigth = heigth;
+        self.restart();
+        Ok(())
+    }
+
+    /// Renders the current viewport again with other settings
+    pub fn set_fractal(&mut self, fractal: &FractalParams, options: &RenderOptions) {
+        self.fractal = *fractal;
+        self.options = options.clone();
+        self.restart();
+    }
+
+    /// Moves the pending tiles closest to pixel (x, y) to the front of the queue,
+    /// coarse tiles still come before refined ones
+    pub fn focus(&mut self, x: f64, y: f64) {
+        let distance = |job: &Job| {
+            let dx = (job.x + job.width / 2) as f64 - x;
+            let dy = (job.y + job.heigth / 2) as f64 - y;
+            dx * dx + dy * dy
+        };
+        self.pending.make_contiguous().sort_by(|a, b| {
+            b.scale
+                .cmp(&a.scale)
+                .then(distance(a).total_cmp(&distance(b)))
+        });
+    }
+
+    /// Renders pending tiles until `budget_ms` milliseconds have passed,
+    /// at least one tile per call so the image always progresses.
+    /// Returns the number of tiles rendered
+    pub fn render(&mut self, budget_ms: f64) -> usize {
+        let start = now();
+        let mut rendered = 0;
+        let histogram = self.options.output == OutputMode::EscapeTime
+            && self.options.coloring == Coloring::Histogram;
+        while let Some(job) = self.pending.pop_front() {
+            let values = self.job_values(job);
+            if !histogram {
+                let tile = self.tile(job, &values, None);
+                self.finished.push_back(tile);
+            } else {
+                self.held.push((job, values));
+                if !self.pending.iter().any(|other| other.scale == job.scale) {
+                    self.release_pass();
+                }
+            }
+            rendered += 1;
+            if now() - start >= budget_ms {
+                break;
+            }
+        }
+        rendered
+    }
+
+    /// the oldest finished tile not pulled yet
+    pub fn next_tile(&mut self) -> Option<Tile> {
+        self.finished.pop_front()
+    }
+
+    pub fn pending_tiles(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// true once every tile of the viewport was rendered at full resolution
+    pub fn is_done(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+impl MandelbrotRenderer {
+    fn restart(&mut self) {
+        self.viewport = self.viewport.wrapping_add(1);
+        self.view_options = self.options.clone();
+        self.view_options.iterations = self.options.iteration_limit(&self.region);
+        self.view_options.auto_iterations = false;
+        self.pending.clear();
+        self.held.clear();
+        self.finished.clear();
+        for scale in [COARSE_SCALE, 1] {
+            for y in (0..self.heigth).step_by(self.tile_size) {
+                for x in (0..self.width).step_by(self.tile_size) {
+                    self.pending.push_back(Job {
+                        x,
+                        y,
+                        width: self.tile_size.min(self.width - x),
+                        heigth: self.tile_size.min(self.heigth - y),
+                        scale,
+                    });
+                }
+            }
+        }
+        self.focus(self.width as f64 / 2.0, self.heigth as f64 / 2.0);
+    }
+
+    //values of the pixels of the tile, the coarse ones repeated over their block
+    fn job_values(&self, job: Job) -> Vec<Option<f64>> {
+        //the coarse pixels sample the top left corner of their block
+        let window = Window {
+            x: job.x,
+            y: job.y,
+            columns: job.width.div_ceil(job.scale),
+            rows: job.heigth.div_ceil(job.scale),
+            step: job.scale,
+        };
+        let values = raw_values(
+            self.width,
+            self.heigth,
+            &self.region,
+            &self.fractal,
+            &self.view_options,
+            window,
+        );
+        if job.scale == 1 {
+            return values;
+        }
+        let mut expanded = Vec::with_capacity(job.width * job.heigth);
+        for j in 0..job.heigth {
+            let row = j / job.scale * window.columns;
+            expanded.extend((0..job.width).map(|i| values[row + i / job.scale]));
+        }
+        expanded
+    }
+
+    fn tile(&self, job: Job, values: &[Option<f64>], cdf: Option<&Histogram>) -> Tile {
+        Tile {
+            x: job.x,
+            y: job.y,
+            width: job.width,
+            height: job.heigth,
+            scale: job.scale,
+            viewport: self.viewport,
+            pixels: paint_values(values, self.width, &self.region, &self.view_options, cdf),
+        }
+    }
+
+    //paints the held tiles of a finished pass with the histogram of all of them
+    fn release_pass(&mut self) {
+        let held = std::mem::take(&mut self.held);
+        let cdf = Histogram::new(held.iter().flat_map(|(_, values)| values));
+        for (job, values) in held {
+            let tile = self.tile(job, &values, Some(&cdf));
+            self.finished.push_back(tile);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{view_region, MandelbrotRenderer, RegionError, COARSE_SCALE};
+    use crate::mandelbrot::{calc_set, Coloring, FractalParams, RenderOptions};
+
+    fn renderer() -> MandelbrotRenderer {
+        let mut renderer =
+            MandelbrotRenderer::new(&FractalParams::default(), &RenderOptions::new());
+        renderer.set_tile_size(16);
+        renderer
+    }
+
+    //refined tiles copied into the image, checking the coarse pass comes first
+    fn assemble(renderer: &mut MandelbrotRenderer, width: usize, heigth: usize) -> Vec<u8> {
+        let coarse_tiles = renderer.pending_tiles() / 2;
+        let mut image = vec![0u8; width * heigth * 4];
+        let mut coarse = 0;
+        while !renderer.is_done() {
+            assert!(renderer.render(0.0) >= 1);
+            while let Some(tile) = renderer.next_tile() {
+                if tile.scale == COARSE_SCALE {
+                    coarse += 1;
+                    continue;
+                }
+                assert_eq!(coarse, coarse_tiles, "the coarse pass comes first");
+                for j in 0..tile.height {
+                    let from = j * tile.width * 4;
+                    let to = ((tile.y + j) * width + tile.x) * 4;
+                    image[to..to + tile.width * 4]
+                        .copy_from_slice(&tile.rgba()[from..from + tile.width * 4]);
+                }
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn refined_tiles_assemble_calc_set() {
+        let region = [-2.0, 1.0, -1.2, 1.2];
+        let (width, heigth) = (40, 30);
+        let mut renderer = renderer();
+        renderer.set_viewport(width, heigth, &region).unwrap();
+        //3 x 2 tiles, coarse then refined
+        assert_eq!(renderer.pending_tiles(), 12);
+        let image = assemble(&mut renderer, width, heigth);
+        let expected = calc_set(
+            width,
+            heigth,
+            &region,
+            &FractalParams::default(),
+            &RenderOptions::new(),
+        );
+        assert_eq!(image, expected);
+    }
+
+    #[test]
+    fn view_wide_limit_and_histogram() {
+        //a zoomed view, so the auto limit of a tile would differ from the one of the view
+        let region = [-0.7467, -0.7447, 0.1012, 0.1027];
+        let (width, heigth) = (70, 45);
+        let fractal = FractalParams::julia(-0.8, 0.156);
+        for fractal in [FractalParams::default(), fractal] {
+            for coloring in [Coloring::Smooth, Coloring::Histogram] {
+                let mut options = RenderOptions::new();
+                options.auto_iterations = true;
+                options.coloring = coloring;
+                let mut renderer = MandelbrotRenderer::new(&fractal, &options);
+                renderer.set_tile_size(16);
+                renderer.set_viewport(width, heigth, &region).unwrap();
+                let image = assemble(&mut renderer, width, heigth);
+                let expected = calc_set(width, heigth, &region, &fractal, &options);
+                assert_eq!(image, expected, "{:?}", coloring);
+            }
+        }
+    }
+
+    #[test]
+    fn coarse_tiles_are_blocks() {
+        let mut renderer = renderer();
+        renderer
+            .set_viewport(20, 10, &[-2.0, 1.0, -1.0, 1.0])
+            .unwrap();
+        renderer.render(0.0);
+        let tile = renderer.next_tile().unwrap();
+        assert_eq!(tile.scale, COARSE_SCALE);
+        assert_eq!(tile.rgba().len(), tile.width * tile.height * 4);
+        let pixel = |x: usize, y: usize| &tile.rgba()[(y * tile.width + x) * 4..][..4];
+        assert_eq!(pixel(0, 0), pixel(7, 7));
+        assert_eq!(pixel(8, 0), pixel(15, 7));
+    }
+
+    #[test]
+    fn new_viewport_cancels_and_focus_reorders() {
+        let mut renderer = renderer();
+        renderer
+            .set_viewport(64, 64, &[-2.0, 1.0, -1.5, 1.5])
+            .unwrap();
+        let first = renderer.viewport();
+        renderer.render(0.0);
+        renderer
+            .set_viewport(64, 64, &[-1.0, 0.0, -0.5, 0.5])
+            .unwrap();
+        assert_ne!(renderer.viewport(), first);
+        //tiles of the old view are dropped, finished or not
+        assert_eq!(renderer.pending_tiles(), 32);
+        assert!(renderer.next_tile().is_none());
+
+        renderer.focus(60.0, 60.0);
+        renderer.render(0.0);
+        let tile = renderer.next_tile().unwrap();
+        assert_eq!((tile.x, tile.y, tile.scale), (48, 48, COARSE_SCALE));
+        assert_eq!(tile.viewport, renderer.viewport());
+        //an unlimited budget finishes the view
+        renderer.render(f64::INFINITY);
+        assert!(renderer.is_done());
+    }
+
+    #[test]
+    fn viewport_needs_four_numbers() {
+        assert_eq!(
+            view_region(&[-2.0, 1.0, -1.0, 1.0]),
+            Ok([-2.0, 1.0, -1.0, 1.0])
+        );
+        assert_eq!(view_region(&[-2.0, 1.0, -1.0]), Err(RegionError { len: 3 }));
+        assert_eq!(view_region(&[]), Err(RegionError { len: 0 }));
+    }
+}
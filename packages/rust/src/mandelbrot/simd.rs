@@ -0,0 +1,216 @@
+//! Escape-time kernel for z² + c iterating two pixels at once in f64 lanes.
+//! The operations are the ones `Complex` does, in the same order, so every lane
+//! escapes at the same iteration with the same z as the scalar loop.
+//! Written once over `Lanes` for wasm `simd128` and x86_64 `sse2`,
+//! elsewhere the lanes are plain arrays.
+
+use super::{Complex, PERIOD_EPSILON2};
+
+/// true when `escape_pair` runs on vector registers
+pub const VECTOR: bool = cfg!(any(
+    all(target_arch = "wasm32", target_feature = "simd128"),
+    all(target_arch = "x86_64", target_feature = "sse2")
+));
+
+/// Iterates z² + c from `z` for both lanes, returning for each the iteration
+/// it left the radius 2 disk at and the z it had then, None if it stayed inside.
+/// With `periodicity` a lane caught in a cycle stops early, like `escape_time`
+pub(super) fn escape_pair(
+    z: [Complex; 2],
+    c: [Complex; 2],
+    limit: u32,
+    periodicity: bool,
+) -> [Option<(u32, Complex)>; 2] {
+    kernel::<arch::F64x2>(z, c, limit, periodicity)
+}
+
+trait Lanes: Copy {
+    fn load(values: [f64; 2]) -> Self;
+    fn store(self) -> [f64; 2];
+    fn add(self, other: Self) -> Self;
+    fn sub(self, other: Self) -> Self;
+    fn mul(self, other: Self) -> Self;
+    /// bit i set when lane i of self is greater than lane i of other
+    fn gt_mask(self, other: Self) -> u8;
+}
+
+fn kernel<V: Lanes>(
+    z: [Complex; 2],
+    c: [Complex; 2],
+    limit: u32,
+    periodicity: bool,
+) -> [Option<(u32, Complex)>; 2] {
+    let mut zr = V::load([z[0].r, z[1].r]);
+    let mut zi = V::load([z[0].i, z[1].i]);
+    let cr = V::load([c[0].r, c[1].r]);
+    let ci = V::load([c[0].i, c[1].i]);
+    let four = V::load([4.0; 2]);
+    let epsilon = V::load([PERIOD_EPSILON2; 2]);
+    let (mut saved_r, mut saved_i) = (zr, zi);
+    let mut next_save = 1;
+    let mut escaped = [None; 2];
+    let mut done = 0u8;
+    for n in 0..limit {
+        let r = zr.mul(zr).sub(zi.mul(zi)).add(cr);
+        let i = zr.mul(zi).add(zi.mul(zr)).add(ci);
+        zr = r;
+        zi = i;
+        let module = zi.mul(zi).add(zr.mul(zr));
+        //escaped lanes keep iterating, their result is already kept
+        let newly = module.gt_mask(four) & !done;
+        if newly != 0 {
+            let (r, i) = (zr.store(), zi.store());
+            for lane in 0..2 {
+                if newly & (1 << lane) != 0 {
+                    escaped[lane] = Some((
+                        n,
+                        Complex {
+                            r: r[lane],
+                            i: i[lane],
+                        },
+                    ));
+                }
+            }
+            done |= newly;
+        }
+        if periodicity {
+            let (dr, di) = (zr.sub(saved_r), zi.sub(saved_i));
+            //lanes in a cycle are done, with no escape
+            done |= epsilon.gt_mask(di.mul(di).add(dr.mul(dr)));
+            if n + 1 == next_save {
+                (saved_r, saved_i) = (zr, zi);
+                next_save *= 2;
+            }
+        }
+        if done == 0b11 {
+            break;
+        }
+    }
+    escaped
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+mod arch {
+    use super::Lanes;
+    use core::arch::wasm32::*;
+
+    #[derive(Clone, Copy)]
+    pub struct F64x2(v128);
+
+    impl Lanes for F64x2 {
+        fn load(values: [f64; 2]) -> Self {
+            F64x2(f64x2(values[0], values[1]))
+        }
+        fn store(self) -> [f64; 2] {
+            [
+                f64x2_extract_lane::<0>(self.0),
+                f64x2_extract_lane::<1>(self.0),
+            ]
+        }
+        fn add(self, other: Self) -> Self {
+            F64x2(f64x2_add(self.0, other.0))
+        }
+        fn sub(self, other: Self) -> Self {
+            F64x2(f64x2_sub(self.0, other.0))
+        }
+        fn mul(self, other: Self) -> Self {
+            F64x2(f64x2_mul(self.0, other.0))
+        }
+        fn gt_mask(self, other: Self) -> u8 {
+            i64x2_bitmask(f64x2_gt(self.0, other.0))
+        }
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+mod arch {
+    use super::Lanes;
+    use core::arch::x86_64::*;
+
+    #[derive(Clone, Copy)]
+    pub struct F64x2(__m128d);
+
+    impl Lanes for F64x2 {
+        fn load(values: [f64; 2]) -> Self {
+            F64x2(unsafe { _mm_set_pd(values[1], values[0]) })
+        }
+        fn store(self) -> [f64; 2] {
+            let mut values = [0.0; 2];
+            unsafe { _mm_storeu_pd(values.as_mut_ptr(), self.0) };
+            values
+        }
+        fn add(self, other: Self) -> Self {
+            F64x2(unsafe { _mm_add_pd(self.0, other.0) })
+        }
+        fn sub(self, other: Self) -> Self {
+            F64x2(unsafe { _mm_sub_pd(self.0, other.0) })
+        }
+        fn mul(self, other: Self) -> Self {
+            F64x2(unsafe { _mm_mul_pd(self.0, other.0) })
+        }
+        fn gt_mask(self, other: Self) -> u8 {
+            unsafe { _mm_movemask_pd(_mm_cmpgt_pd(self.0, other.0)) as u8 }
+        }
+    }
+}
+
+#[cfg(not(any(
+    all(target_arch = "wasm32", target_feature = "simd128"),
+    all(target_arch = "x86_64", target_feature = "sse2")
+)))]
+mod arch {
+    use super::Lanes;
+
+    #[derive(Clone, Copy)]
+    pub struct F64x2([f64; 2]);
+
+    impl Lanes for F64x2 {
+        fn load(values: [f64; 2]) -> Self {
+            F64x2(values)
+        }
+        fn store(self) -> [f64; 2] {
+            self.0
+        }
+        fn add(self, other: Self) -> Self {
+            F64x2([self.0[0] + other.0[0], self.0[1] + other.0[1]])
+        }
+        fn sub(self, other: Self) -> Self {
+            F64x2([self.0[0] - other.0[0], self.0[1] - other.0[1]])
+        }
+        fn mul(self, other: Self) -> Self {
+            F64x2([self.0[0] * other.0[0], self.0[1] * other.0[1]])
+        }
+        fn gt_mask(self, other: Self) -> u8 {
+            (self.0[0] > other.0[0]) as u8 | ((self.0[1] > other.0[1]) as u8) << 1
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::escape_pair;
+    use crate::mandelbrot::fractal::{Fractal, Mandelbrot};
+    use crate::mandelbrot::Complex;
+    use crate::rng::SeededRng;
+
+    #[test]
+    fn lanes_match_scalar() {
+        let mut rng = SeededRng::new(19);
+        let mut random = || rng.next_f64();
+        for _ in 0..2000 {
+            let c = [0, 1].map(|_| Complex {
+                r: random() * 3.0 - 2.0,
+                i: random() * 3.0 - 1.5,
+            });
+            let result = escape_pair([Complex::ZERO; 2], c, 300, false);
+            for lane in 0..2 {
+                let mut z = Complex::ZERO;
+                let expected = (0..300).find_map(|n| {
+                    z = Mandelbrot.step(z, c[lane]);
+                    (z.module() > 4.0).then_some((n, z))
+                });
+                assert_eq!(result[lane], expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Mariani–Silver subdivision: when the border of a rectangle has a single escape
+//! value the whole rectangle gets it without iterating, otherwise it is split in
+//! four and each part is tried again. The Mandelbrot set is connected and has no
+//! holes, so a border inside the set only encloses the set, and an escaping border
+//! only encloses the set when the whole set is inside, around the origin: those
+//! rectangles and the whole view are always split.
+//! Other fractals only take this path when `Fractal::connected` says the same
+
+//rectangles this small are computed pixel by pixel
+const MIN_SIDE: usize = 6;
+
+/// escape value of every pixel of a `width` x `heigth` image, row by row,
+/// `pixel` being called only on the pixels that can't be filled from a border.
+/// `holds_set(x0, y0, x1, y1)` tells when the rectangle between those pixels
+/// may enclose the whole set
+pub(super) fn subdivide(
+    width: usize,
+    heigth: usize,
+    pixel: impl Fn(usize, usize) -> Option<f64>,
+    holds_set: impl Fn(usize, usize, usize, usize) -> bool,
+) -> Vec<Option<f64>> {
+    let mut grid = Grid {
+        width,
+        values: vec![None; width * heigth],
+        known: vec![false; width * heigth],
+        pixel,
+        holds_set,
+    };
+    if width > 0 && heigth > 0 {
+        grid.split(0, 0, width - 1, heigth - 1);
+    }
+    grid.values
+}
+
+struct Grid<P, H> {
+    width: usize,
+    values: Vec<Option<f64>>,
+    //pixels already computed or filled, shared borders are only computed once
+    known: Vec<bool>,
+    pixel: P,
+    holds_set: H,
+}
+
+impl<P, H> Grid<P, H>
+where
+    P: Fn(usize, usize) -> Option<f64>,
+    H: Fn(usize, usize, usize, usize) -> bool,
+{
+    fn get(&mut self, x: usize, y: usize) -> Option<f64> {
+        let index = y * self.width + x;
+        if !self.known[index] {
+            self.values[index] = (self.pixel)(x, y);
+            self.known[index] = true;
+        }
+        self.values[index]
+    }
+
+    //fills the rectangle between the corners, both included
+    fn fill(&mut self, x0: usize, y0: usize, x1: usize, y1: usize) {
+        if x1 - x0 < MIN_SIDE || y1 - y0 < MIN_SIDE {
+            return self.split(x0, y0, x1, y1);
+        }
+        let first = self.get(x0, y0);
+        let mut uniform = true;
+        for x in x0..=x1 {
+            uniform &= self.get(x, y0) == first;
+            uniform &= self.get(x, y1) == first;
+        }
+        for y in y0..=y1 {
+            uniform &= self.get(x0, y) == first;
+            uniform &= self.get(x1, y) == first;
+        }
+        if uniform && (first.is_none() || !(self.holds_set)(x0, y0, x1, y1)) {
+            for y in y0 + 1..y1 {
+                let row = y * self.width;
+                self.values[row + x0 + 1..row + x1].fill(first);
+                self.known[row + x0 + 1..row + x1].fill(true);
+            }
+            return;
+        }
+        self.split(x0, y0, x1, y1);
+    }
+
+    //fills the four quarters of the rectangle, computing small ones pixel by pixel
+    fn split(&mut self, x0: usize, y0: usize, x1: usize, y1: usize) {
+        if x1 - x0 < MIN_SIDE || y1 - y0 < MIN_SIDE {
+            for y in y0..=y1 {
+                for x in x0..=x1 {
+                    self.get(x, y);
+                }
+            }
+            return;
+        }
+        //the halves share the middle row and column, already known by then
+        let (mx, my) = ((x0 + x1) / 2, (y0 + y1) / 2);
+        self.fill(x0, y0, mx, my);
+        self.fill(mx, y0, x1, my);
+        self.fill(x0, my, mx, y1);
+        self.fill(mx, my, x1, y1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::subdivide;
+    use std::cell::Cell;
+
+    #[test]
+    fn fills_uniform_rectangles() {
+        let calls = Cell::new(0);
+        //a disk of radius 40 around a corner of a 64 x 64 image,
+        //a shape with a hole would be lost when its border is uniform
+        let disk = |x: usize, y: usize| {
+            calls.set(calls.get() + 1);
+            let (dx, dy) = (x as f64, y as f64);
+            (dx * dx + dy * dy > 1600.0).then_some(1.0)
+        };
+        let values = subdivide(64, 64, disk, |_, _, _, _| false);
+        let calls_made = calls.get();
+        calls.set(0);
+        let expected: Vec<_> = (0..64 * 64).map(|i| disk(i % 64, i / 64)).collect();
+        assert_eq!(values, expected);
+        assert!(calls_made < 64 * 64 / 2, "{}", calls_made);
+        assert!(subdivide(0, 5, disk, |_, _, _, _| false).is_empty());
+        assert_eq!(
+            subdivide(3, 1, |x, _| Some(x as f64), |_, _, _, _| false),
+            [Some(0.0), Some(1.0), Some(2.0)]
+        );
+    }
+
+    #[test]
+    fn keeps_a_set_inside_a_uniform_border() {
+        //a disk of radius 10 in the middle, every border of the view escapes alike
+        let disk = |x: usize, y: usize| {
+            let (dx, dy) = (x as f64 - 40.0, y as f64 - 30.0);
+            (dx * dx + dy * dy > 100.0).then_some(1.0)
+        };
+        let around = |x0, y0, x1, y1| (x0..=x1).contains(&40) && (y0..=y1).contains(&30);
+        let values = subdivide(80, 64, disk, around);
+        let expected: Vec<_> = (0..80 * 64).map(|i| disk(i % 80, i / 80)).collect();
+        assert_eq!(values, expected);
+    }
+}
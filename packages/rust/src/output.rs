@@ -0,0 +1,88 @@
+use wasm_bindgen::prelude::*;
+
+/// Location of an encoder's last output inside wasm memory.
+/// JS reads it with `new Uint8Array(memory.buffer, view.ptr, view.len)`.
+/// The output buffer keeps its capacity between calls, so `ptr` usually stays the same,
+/// but when it has to grow `generation` changes and views made before are no longer valid.
+/// Any allocation can also grow the wasm memory and detach `memory.buffer`,
+/// so a view should be read before calling into wasm again.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputView {
+    ptr: *const u8,
+    len: usize,
+    generation: u32,
+}
+
+#[wasm_bindgen]
+impl OutputView {
+    #[wasm_bindgen(getter)]
+    pub fn ptr(&self) -> *const u8 {
+        self.ptr
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+/// Counts how many times an output buffer was reallocated
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Generation {
+    ptr: usize,
+    capacity: usize,
+    count: u32,
+}
+
+impl Generation {
+    pub fn track(&mut self, bytes: &Vec<u8>) -> OutputView {
+        let (ptr, capacity) = (bytes.as_ptr() as usize, bytes.capacity());
+        if (ptr, capacity) != (self.ptr, self.capacity) {
+            self.ptr = ptr;
+            self.capacity = capacity;
+            self.count = self.count.wrapping_add(1);
+        }
+        self.view(bytes)
+    }
+
+    pub fn view(&self, bytes: &[u8]) -> OutputView {
+        OutputView {
+            ptr: bytes.as_ptr(),
+            len: bytes.len(),
+            generation: self.count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Generation;
+
+    #[test]
+    fn generation_changes_on_reallocation() {
+        let mut generation = Generation::default();
+        let mut bytes = Vec::with_capacity(16);
+        bytes.extend_from_slice(&[1, 2, 3]);
+        let first = generation.track(&bytes);
+        bytes.clear();
+        bytes.extend_from_slice(&[4; 16]);
+        let same = generation.track(&bytes);
+        assert_eq!(first.generation(), same.generation());
+        assert_eq!(same.len(), 16);
+        bytes.extend_from_slice(&[5; 64]);
+        let grown = generation.track(&bytes);
+        assert_ne!(first.generation(), grown.generation());
+        assert_eq!(grown.ptr(), bytes.as_ptr());
+    }
+}
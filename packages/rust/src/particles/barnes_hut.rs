@@ -0,0 +1,240 @@
+//! Barnes–Hut octree: particles are grouped in nested cubes and a cube seen under an
+//! angle smaller than θ (its side over its distance) acts as a single particle at its
+//! center of mass, weighing as many particles as it holds. O(n log n) instead of O(n²)
+
+use super::euler::V4;
+
+//particles a leaf holds before it is split, they are visited one by one
+const LEAF_SIZE: usize = 8;
+//coincident particles can't be split apart, stop there
+const MAX_DEPTH: u32 = 24;
+
+struct Node {
+    mass_center: V4,
+    count: f32,
+    //center and side of the cube
+    center: [f32; 3],
+    size: f32,
+    children: [u32; 8],
+    //0 for leaves, which hold `order[start..end]`
+    child_count: u8,
+    start: u32,
+    end: u32,
+}
+
+impl Node {
+    fn contains(&self, point: &V4) -> bool {
+        let half = self.size / 2.0;
+        [point.x(), point.y(), point.z()]
+            .into_iter()
+            .zip(self.center)
+            .all(|(value, center)| (value - center).abs() <= half)
+    }
+}
+
+pub(super) struct Octree<'a> {
+    positions: &'a [V4],
+    //indices of the particles, those of a node next to each other
+    order: Vec<usize>,
+    nodes: Vec<Node>,
+}
+
+impl<'a> Octree<'a> {
+    pub fn new(positions: &'a [V4]) -> Self {
+        let mut tree = Octree {
+            positions,
+            order: (0..positions.len()).collect(),
+            nodes: Vec::with_capacity(positions.len() / 2 + 1),
+        };
+        if positions.is_empty() {
+            return tree;
+        }
+        let (mut min, mut max) = ([f32::MAX; 3], [f32::MIN; 3]);
+        for p in positions {
+            for (axis, value) in [p.x(), p.y(), p.z()].into_iter().enumerate() {
+                min[axis] = min[axis].min(value);
+                max[axis] = max[axis].max(value);
+            }
+        }
+        let center = [0, 1, 2].map(|axis| (min[axis] + max[axis]) / 2.0);
+        let half = (0..3)
+            .map(|axis| (max[axis] - min[axis]) / 2.0)
+            .fold(f32::MIN_POSITIVE, f32::max);
+        tree.build(0, positions.len(), center, half, 0);
+        tree
+    }
+
+    //node of the particles `order[start..end]`, all in the cube of `center` and half side `half`
+    fn build(&mut self, start: usize, end: usize, center: [f32; 3], half: f32, depth: u32) -> u32 {
+        let positions = self.positions;
+        let particles = &mut self.order[start..end];
+        let sum = particles.iter().fold([0.0; 3], |sum, &i| {
+            let p = &positions[i];
+            [sum[0] + p.x(), sum[1] + p.y(), sum[2] + p.z()]
+        });
+        let count = particles.len() as f32;
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            mass_center: V4::xyz(sum[0] / count, sum[1] / count, sum[2] / count),
+            count,
+            center,
+            size: half * 2.0,
+            children: [0; 8],
+            child_count: 0,
+            start: start as u32,
+            end: end as u32,
+        });
+        if particles.len() <= LEAF_SIZE || depth == MAX_DEPTH {
+            return index as u32;
+        }
+
+        let octant = |i: &usize| {
+            let p = &positions[*i];
+            (p.x() >= center[0]) as usize
+                | ((p.y() >= center[1]) as usize) << 1
+                | ((p.z() >= center[2]) as usize) << 2
+        };
+        particles.sort_unstable_by_key(octant);
+        let mut counts = [0; 8];
+        particles.iter().for_each(|i| counts[octant(i)] += 1);
+        let mut from = start;
+        for (k, &count) in counts.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let offset = |bit: usize| {
+                if k & bit == 0 {
+                    -half / 2.0
+                } else {
+                    half / 2.0
+                }
+            };
+            let child_center = [
+                center[0] + offset(1),
+                center[1] + offset(2),
+                center[2] + offset(4),
+            ];
+            let child = self.build(from, from + count, child_center, half / 2.0, depth + 1);
+            let node = &mut self.nodes[index];
+            node.children[node.child_count as usize] = child;
+            node.child_count += 1;
+            from += count;
+        }
+        index as u32
+    }
+
+    /// Calls `f` with the particles acting on `point` and how many particles each stands for:
+    /// the particles themselves when close, the centers of mass of the cubes seen under
+    /// an angle below `theta` otherwise. Cubes holding `point` are always opened, so a
+    /// particle is never merged with the others of its cube. With `theta` 0 every particle is visited
+    pub fn visit(&self, point: &V4, theta: f32, mut f: impl FnMut(&V4, f32)) {
+        if !self.nodes.is_empty() {
+            self.visit_node(0, point, theta * theta, &mut f);
+        }
+    }
+
+    fn visit_node(&self, index: u32, point: &V4, theta2: f32, f: &mut impl FnMut(&V4, f32)) {
+        let node = &self.nodes[index as usize];
+        if node.child_count == 0 {
+            self.order[node.start as usize..node.end as usize]
+                .iter()
+                .for_each(|&i| f(&self.positions[i], 1.0));
+        } else if !node.contains(point)
+            && node.size * node.size < theta2 * point.sub(&node.mass_center).norm_squared()
+        {
+            f(&node.mass_center, node.count);
+        } else {
+            node.children[..node.child_count as usize]
+                .iter()
+                .for_each(|&child| self.visit_node(child, point, theta2, f));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Octree;
+    use crate::particles::euler::V4;
+    use crate::particles::ParticleWorldCalc;
+    use crate::rng::SeededRng;
+
+    fn cloud(n: usize) -> Vec<V4> {
+        let mut rng = SeededRng::new(24);
+        let mut random = || rng.next_f64() as f32;
+        //a dense cluster inside a sparse cloud, so the tree gets uneven
+        (0..n)
+            .map(|i| {
+                let scale = if i % 3 == 0 { 5.0 } else { 100.0 };
+                V4::xyz(random() * scale, random() * scale, random() * scale)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn visits_every_particle_once() {
+        let positions = cloud(1000);
+        let tree = Octree::new(&positions);
+        for theta in [0.0, 0.5, 1.0] {
+            let mut weight = 0.0;
+            tree.visit(&positions[0], theta, |_, count| weight += count);
+            assert_eq!(weight, 1000.0);
+        }
+        let mut visited = 0;
+        tree.visit(&positions[0], 0.0, |_, _| visited += 1);
+        assert_eq!(visited, 1000);
+        Octree::new(&[]).visit(&positions[0], 0.5, |_, _| panic!());
+        //coincident particles stop splitting
+        let same = vec![V4::xyz(1.0, 2.0, 3.0); 50];
+        let mut weight = 0.0;
+        Octree::new(&same).visit(&same[0], 0.5, |_, count| weight += count);
+        assert_eq!(weight, 50.0);
+    }
+
+    #[test]
+    fn cubes_holding_the_point_are_opened() {
+        //a tight cluster in one corner, the point alone in the opposite one: the root
+        //is small next to the distance to its center of mass but must not be merged
+        let mut positions: Vec<_> = (0..20)
+            .map(|i| V4::xyz(i as f32 * 0.001, 0.0, 0.0))
+            .collect();
+        let point = V4::xyz(1.0, 1.0, 1.0);
+        positions.push(point.clone());
+        let tree = Octree::new(&positions);
+        for theta in [1.0, 10.0] {
+            let mut weight = 0.0;
+            let mut itself = false;
+            tree.visit(&point, theta, |p, count| {
+                weight += count;
+                itself |= count == 1.0 && *p == point;
+            });
+            assert_eq!(weight, 21.0);
+            assert!(itself, "{}", theta);
+        }
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let positions = cloud(2000);
+        let speed = vec![V4::xyz(0.0, 0.0, 0.0); positions.len()];
+        let mut calc = ParticleWorldCalc {
+            center: V4::xyz(50.0, 50.0, 0.0),
+            repulsion: 200.0,
+            center_force: 1.5,
+            theta: 0.0,
+            cutoff: f32::INFINITY,
+        };
+        let exact = calc.calc_acc(&positions, &speed);
+        let rms = (exact.iter().map(|a| a.norm_squared()).sum::<f32>() / exact.len() as f32).sqrt();
+        for (theta, tolerance) in [(0.3, 0.002), (0.5, 0.01), (0.8, 0.05)] {
+            calc.theta = theta;
+            let approximate = calc.calc_acc(&positions, &speed);
+            let error = exact
+                .iter()
+                .zip(&approximate)
+                .map(|(a, b)| a.sub(b).norm())
+                .sum::<f32>()
+                / exact.len() as f32;
+            assert!(error < tolerance * rms, "{} {} {}", theta, error, rms);
+        }
+    }
+}
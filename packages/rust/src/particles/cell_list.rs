@@ -0,0 +1,174 @@
+//! Cell list for forces with a cutoff radius: a uniform grid of cells at least as wide
+//! as the cutoff, so the particles within the cutoff of a point are in its cell or one
+//! of the 26 around it. Rebuilt from the positions every step
+
+use super::euler::V4;
+
+pub(super) struct CellList<'a> {
+    positions: &'a [V4],
+    cutoff2: f32,
+    min: [f32; 3],
+    cell_size: f32,
+    dims: [usize; 3],
+    //particles of cell k are `order[starts[k]..starts[k + 1]]`, by increasing index
+    starts: Vec<usize>,
+    order: Vec<usize>,
+}
+
+impl<'a> CellList<'a> {
+    pub fn new(positions: &'a [V4], cutoff: f32) -> Self {
+        let (mut min, mut max) = ([0.0f32; 3], [0.0f32; 3]);
+        if !positions.is_empty() {
+            (min, max) = ([f32::MAX; 3], [f32::MIN; 3]);
+        }
+        for p in positions {
+            for (axis, value) in [p.x(), p.y(), p.z()].into_iter().enumerate() {
+                min[axis] = min[axis].min(value);
+                max[axis] = max[axis].max(value);
+            }
+        }
+        let extent = [0, 1, 2].map(|axis| max[axis] - min[axis]);
+        //a small cutoff would make more cells than particles, larger cells find the same pairs
+        let max_cells = ((positions.len() as f32).cbrt().ceil() as usize * 2).max(1);
+        let widest = extent.into_iter().fold(0.0, f32::max);
+        let cell_size = cutoff.max(widest / max_cells as f32).max(f32::MIN_POSITIVE);
+        let dims = extent.map(|e| ((e / cell_size).ceil() as usize).clamp(1, max_cells));
+
+        let mut cells = CellList {
+            positions,
+            cutoff2: cutoff * cutoff,
+            min,
+            cell_size,
+            dims,
+            starts: vec![0; dims[0] * dims[1] * dims[2] + 1],
+            order: vec![0; positions.len()],
+        };
+        //counting sort, which keeps the indices of a cell in order
+        let keys: Vec<usize> = positions.iter().map(|p| cells.key(cells.cell(p))).collect();
+        keys.iter().for_each(|&key| cells.starts[key + 1] += 1);
+        for k in 1..cells.starts.len() {
+            cells.starts[k] += cells.starts[k - 1];
+        }
+        let mut next = cells.starts.clone();
+        for (i, &key) in keys.iter().enumerate() {
+            cells.order[next[key]] = i;
+            next[key] += 1;
+        }
+        cells
+    }
+
+    fn cell(&self, p: &V4) -> [usize; 3] {
+        let coordinates = [p.x(), p.y(), p.z()];
+        [0, 1, 2].map(|axis| {
+            let cell = ((coordinates[axis] - self.min[axis]) / self.cell_size).max(0.0) as usize;
+            cell.min(self.dims[axis] - 1)
+        })
+    }
+
+    fn key(&self, cell: [usize; 3]) -> usize {
+        (cell[2] * self.dims[1] + cell[1]) * self.dims[0] + cell[0]
+    }
+
+    /// Calls `f` with every particle within the cutoff of `point`, itself included
+    pub fn visit(&self, point: &V4, mut f: impl FnMut(&V4)) {
+        let cell = self.cell(point);
+        let around =
+            |axis: usize| cell[axis].saturating_sub(1)..=(cell[axis] + 1).min(self.dims[axis] - 1);
+        for z in around(2) {
+            for y in around(1) {
+                for x in around(0) {
+                    let key = self.key([x, y, z]);
+                    for &i in &self.order[self.starts[key]..self.starts[key + 1]] {
+                        let other = &self.positions[i];
+                        if point.sub(other).norm_squared() <= self.cutoff2 {
+                            f(other);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CellList;
+    use crate::particles::euler::V4;
+    use crate::particles::ParticleWorldCalc;
+    use crate::rng::SeededRng;
+
+    fn cloud(seed: u64, n: usize) -> Vec<V4> {
+        let mut rng = SeededRng::new(seed);
+        let mut random = || rng.next_f64() as f32;
+        (0..n)
+            .map(|_| V4::xyz(random() * 100.0, random() * 60.0, random() * 30.0))
+            .collect()
+    }
+
+    #[test]
+    fn finds_the_particles_within_the_cutoff() {
+        let positions = cloud(1, 1500);
+        for cutoff in [0.0, 3.0, 10.0, 40.0] {
+            let cells = CellList::new(&positions, cutoff);
+            for point in positions.iter().step_by(37) {
+                let mut found = vec![];
+                cells.visit(point, |other| found.push(other.clone()));
+                let expected: Vec<V4> = positions
+                    .iter()
+                    .filter(|other| point.sub(other).norm_squared() <= cutoff * cutoff)
+                    .cloned()
+                    .collect();
+                assert_eq!(found.len(), expected.len(), "{}", cutoff);
+                assert!(expected.iter().all(|p| found.contains(p)));
+            }
+        }
+        CellList::new(&[], 1.0).visit(&positions[0], |_| panic!());
+    }
+
+    #[test]
+    fn cutoff_matches_brute_force() {
+        let positions = cloud(2, 800);
+        let speed: Vec<V4> = cloud(3, 800).iter().map(|v| v.mul_scalar(0.01)).collect();
+        let mut calc = ParticleWorldCalc {
+            center: V4::xyz(50.0, 50.0, 0.0),
+            repulsion: 200.0,
+            center_force: 1.5,
+            theta: 0.0,
+            cutoff: f32::INFINITY,
+        };
+        let exact = calc.calc_acc(&positions, &speed);
+        //the diagonal of the world is below 125
+        calc.cutoff = 125.0;
+        assert_eq!(calc.calc_acc(&positions, &speed), exact);
+        //the cell list takes over from the tree
+        calc.theta = 0.8;
+        assert_eq!(calc.calc_acc(&positions, &speed), exact);
+
+        //shorter cutoffs spread the particles over many cells and drop the far pairs,
+        //the others are summed in another order than one by one
+        calc.theta = 0.0;
+        for cutoff in [4.0, 9.0, 20.0] {
+            let cells = CellList::new(&positions, cutoff);
+            assert!(cells.dims.iter().product::<usize>() >= 8, "{}", cutoff);
+            calc.cutoff = cutoff;
+            let short = calc.calc_acc(&positions, &speed);
+            assert_ne!(short, exact);
+            let by_hand: Vec<V4> = positions
+                .iter()
+                .zip(&speed)
+                .map(|(x, v)| {
+                    let mut acc = calc.base_influence(x);
+                    acc.add_mut(&v.mul_scalar(-super::super::DAMPING));
+                    positions
+                        .iter()
+                        .filter(|other| x.sub(other).norm_squared() <= cutoff * cutoff)
+                        .for_each(|other| acc.add_mut(&calc.influence(x, other)));
+                    acc
+                })
+                .collect();
+            for (a, b) in short.iter().zip(&by_hand) {
+                assert!(a.sub(b).norm() <= 1e-5 * b.norm().max(1.0), "{}", cutoff);
+            }
+        }
+    }
+}
@@ -34,6 +34,7 @@ impl V4 {
         }
     }
 
+    #[cfg(test)]
     pub fn is_close_to(&self, other: &V4) -> bool {
         self.sub(other).norm_squared() < 0.001
     }
@@ -134,6 +135,7 @@ impl Mat4 {
         mat
     }
 
+    #[cfg(test)]
     pub fn rotate_x(angle: f32) -> Self {
         let mut mat = Self::identity();
         let cos = angle.cos();
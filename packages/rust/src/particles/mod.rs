@@ -1,8 +1,12 @@
+mod barnes_hut;
+mod cell_list;
 mod euler;
 
 use self::euler::Mat4;
 
 use super::random;
+use barnes_hut::Octree;
+use cell_list::CellList;
 use euler::{euler_evolve, V4};
 use wasm_bindgen::prelude::*;
 
@@ -16,12 +20,14 @@ pub struct ParticleWorld {
 
 #[wasm_bindgen]
 pub fn random_world(max_x: f32, max_y: f32, number_of_particles: usize) -> ParticleWorld {
+    //the particles start in a 100 wide cube whatever the size, which JS still passes
+    let _ = (max_x, max_y);
     let v = (0..number_of_particles).map(|_| {
-        return V4::xyz(
+        V4::xyz(
             random() as f32 * 100.0,
             random() as f32 * 100.0,
             100.0 * random() as f32,
-        );
+        )
     });
 
     ParticleWorld {
@@ -32,6 +38,8 @@ pub fn random_world(max_x: f32, max_y: f32, number_of_particles: usize) -> Parti
             center: V4::xyz(0.0, 0.0, 0.0),
             repulsion: 200.0,
             center_force: 1.5,
+            theta: 0.0,
+            cutoff: f32::INFINITY,
         },
     }
 }
@@ -48,6 +56,8 @@ impl ParticleWorld {
     }
 
     pub fn rotate(&mut self, angle_x: f32, angle_y: f32) {
+        //only the rotation around y is applied, `angle_x` is kept for the JS signature
+        let _ = angle_x;
         let v_rotated = Mat4::rotate_y(angle_y).v_mul(&V4::xyz(0.0, 0.0, 1000.0));
         // let v_rotated = Mat4::rotate_x(angle_x).v_mul(&v_rotated);
         let translate = Mat4::translation_mat(0.0, 0.0, 0.0);
@@ -73,9 +83,18 @@ impl ParticleWorld {
         self.calc.center = V4::xyz(x, y, 0.0);
     }
 
-    pub fn set_forces(&mut self, repulsion: f32, center: f32) {
+    /// `cutoff` is the distance past which particles stop repelling each other, `Infinity`
+    /// for none. A finite cutoff only visits the neighbouring cells of a grid
+    pub fn set_forces(&mut self, repulsion: f32, center: f32, cutoff: f32) {
         self.calc.repulsion = repulsion;
         self.calc.center_force = center;
+        self.calc.cutoff = cutoff.max(0.0);
+    }
+
+    /// opening angle of the Barnes–Hut approximation of the repulsion, about 0.5 keeps it
+    /// within a percent. 0 computes every pair exactly. Unused with a finite cutoff
+    pub fn set_theta(&mut self, theta: f32) {
+        self.calc.theta = theta.max(0.0);
     }
 }
 
@@ -83,20 +102,35 @@ struct ParticleWorldCalc {
     center: V4,
     repulsion: f32,
     center_force: f32,
+    theta: f32,
+    cutoff: f32,
 }
 
 impl ParticleWorldCalc {
     #[inline(never)]
-    fn calc_acc(&self, position: &Vec<V4>, speed: &Vec<V4>) -> Vec<V4> {
+    fn calc_acc(&self, position: &[V4], speed: &[V4]) -> Vec<V4> {
+        let cells = self
+            .cutoff
+            .is_finite()
+            .then(|| CellList::new(position, self.cutoff));
+        let tree = (cells.is_none() && self.theta > 0.0).then(|| Octree::new(position));
         position
             .iter()
             .enumerate()
             .map(|(i, x)| {
-                let mut acc = self.base_influence(&x);
+                let mut acc = self.base_influence(x);
                 acc.add_mut(&speed[i].mul_scalar(-DAMPING)); //speed damping
-                position
-                    .iter()
-                    .for_each(|other| acc.add_mut(&self.influence(&x, &other)));
+                match (&cells, &tree) {
+                    (Some(cells), _) => {
+                        cells.visit(x, |other| acc.add_mut(&self.influence(x, other)))
+                    }
+                    (_, Some(tree)) => tree.visit(x, self.theta, |other, count| {
+                        acc.add_mut(&self.influence(x, other).mul_scalar(count))
+                    }),
+                    _ => position
+                        .iter()
+                        .for_each(|other| acc.add_mut(&self.influence(x, other))),
+                }
                 acc
             })
             .collect()
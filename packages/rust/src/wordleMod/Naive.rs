@@ -26,7 +26,7 @@ pub fn calc_best_guesses(words: &[ByteStr], candidates: &[ByteStr]) -> Vec<(Byte
 
 pub fn guess_information(words: &[ByteStr], guess: &Guess) -> f64 {
     let after_guess_count = words.iter().filter(|&&word| guess.matches(&word)).count();
-    if words.len() != 0 {
+    if !words.is_empty() {
         (after_guess_count as f64 / words.len() as f64).log2().neg()
     } else {
         0.0
@@ -47,5 +47,5 @@ pub fn entropy_of(guess_word: &ByteStr, valid_words: &[ByteStr]) -> f64 {
 // pub fn calc_best_guesses(words: &[ByteStr]) -> Vec<(ByteStr, f64)> {}
 
 fn calc_information(probability: f64) -> f64 {
-    probability as f64 * probability.log2().abs()
+    probability * probability.log2().abs()
 }
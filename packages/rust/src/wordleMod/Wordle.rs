@@ -53,11 +53,13 @@ impl Wordle {
         panic!("Max guesses reached");
     }
 
+    //from_serde keeps the JSON shape the page reads
+    #[allow(deprecated)]
     pub fn play(&mut self, guess_word: &str) -> JsValue {
         let guess_word = str5(guess_word);
         let correcness = Correctness::check(&self.answer, &guess_word);
         let guess = Guess {
-            mask: correcness.clone(),
+            mask: correcness,
             word: guess_word,
         };
         let information_gain = Naive::guess_information(&self.available_words(), &guess);
@@ -71,6 +73,7 @@ impl Wordle {
         *self = Wordle::new(None, "hello");
     }
 
+    #[allow(deprecated)]
     pub fn calc_best_guesses(&self) -> JsValue {
         let words = self.available_words();
         let random_words: Vec<_> = (0..500)
@@ -100,7 +103,7 @@ impl Wordle {
 
 fn get_random_word(words: &[ByteStr]) -> Option<ByteStr> {
     let index = (words.len() as f64 * random()).round() as usize;
-    words.get(index).map(|str| *str)
+    words.get(index).copied()
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -179,7 +182,7 @@ impl Correctness {
     }
 }
 
-pub fn filter_with<'a>(all_words: &[ByteStr], history: &[Guess]) -> Vec<ByteStr> {
+pub fn filter_with(all_words: &[ByteStr], history: &[Guess]) -> Vec<ByteStr> {
     all_words
         .iter()
         .filter(|&&word| {
@@ -187,7 +190,7 @@ pub fn filter_with<'a>(all_words: &[ByteStr], history: &[Guess]) -> Vec<ByteStr>
                 .iter()
                 .all(|guess| guess.matches(&word) && guess.word != word)
         })
-        .map(|&str| str)
+        .copied()
         .collect()
 }
 
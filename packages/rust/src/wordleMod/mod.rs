@@ -28,7 +28,7 @@ impl Guess {
         valid_words.iter().for_each(|word| {
             let mask = Correctness::check(word, guess_word);
             let mask_radix = Correctness::mask_radix(&mask);
-            map_arr[mask_radix] = map_arr[mask_radix] + 1;
+            map_arr[mask_radix] += 1;
         });
         map_arr
     }